@@ -1,7 +1,7 @@
 with import <nixpkgs> {};
 stdenv.mkDerivation {
     name = "cargo-env";
-    buildInputs = [ pkgconfig openssl sqlite ];
+    buildInputs = [ pkgconfig openssl sqlite postgresql poppler_utils ];
     shellHook = ''
         PATH="$PATH:~/.cargo/bin"
     '';
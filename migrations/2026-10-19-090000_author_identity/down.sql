@@ -0,0 +1,16 @@
+CREATE TABLE authors_legacy (
+  arxiv_id TEXT NOT NULL,
+  auth     TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, auth),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO authors_legacy (arxiv_id, auth)
+  SELECT arxiv_id, auth FROM author_papers ORDER BY rowid;
+
+DROP TABLE author_papers;
+DROP TABLE author_rekey;
+DROP TABLE author_variants;
+DROP TABLE authors;
+
+ALTER TABLE authors_legacy RENAME TO authors;
@@ -0,0 +1,62 @@
+ALTER TABLE authors RENAME TO authors_legacy;
+
+CREATE TABLE authors (
+  id            INTEGER NOT NULL,
+  name          TEXT NOT NULL,
+  norm_name     TEXT NOT NULL,
+  family        TEXT NOT NULL,
+  orcid         TEXT,
+  arxiv_auth_id TEXT,
+  PRIMARY KEY(id),
+  UNIQUE(norm_name)
+);
+
+CREATE INDEX authors_family ON authors(family);
+
+CREATE TABLE author_variants (
+  author_id INTEGER NOT NULL,
+  variant   TEXT NOT NULL,
+  PRIMARY KEY(author_id, variant),
+  FOREIGN KEY(author_id) REFERENCES authors(id) ON DELETE CASCADE
+);
+
+CREATE TABLE author_rekey (
+  author_id INTEGER NOT NULL,
+  PRIMARY KEY(author_id),
+  FOREIGN KEY(author_id) REFERENCES authors(id) ON DELETE CASCADE
+);
+
+CREATE TABLE author_papers (
+  arxiv_id  TEXT NOT NULL,
+  author_id INTEGER NOT NULL,
+  auth      TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, author_id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE,
+  FOREIGN KEY(author_id) REFERENCES authors(id)
+);
+
+-- The normalisation here is an approximation of `Author::key`, the keys of the
+-- authors listed in author_rekey are made again by the bot on start, see
+-- `db::author::rekey`. The raw names are kept as variants so that the bot still
+-- recognises them afterwards.
+INSERT OR IGNORE INTO authors (name, norm_name, family)
+  SELECT auth,
+         lower(replace(trim(auth), '.', '')),
+         lower(replace(trim(auth), rtrim(trim(auth), replace(trim(auth), ' ', '')), ''))
+  FROM authors_legacy;
+
+INSERT OR IGNORE INTO author_variants (author_id, variant)
+  SELECT a.id, l.auth
+  FROM authors_legacy l
+  JOIN authors a ON a.norm_name = lower(replace(trim(l.auth), '.', ''));
+
+-- Rows of the legacy table were inserted in author order
+INSERT OR IGNORE INTO author_papers (arxiv_id, author_id, auth)
+  SELECT l.arxiv_id, v.author_id, l.auth
+  FROM authors_legacy l
+  JOIN author_variants v ON v.variant = l.auth
+  ORDER BY l.rowid;
+
+INSERT INTO author_rekey (author_id) SELECT id FROM authors;
+
+DROP TABLE authors_legacy;
@@ -0,0 +1,15 @@
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE author_papers_new (
+  arxiv_id  TEXT NOT NULL,
+  author_id INTEGER NOT NULL,
+  auth      TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, author_id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE,
+  FOREIGN KEY(author_id) REFERENCES authors(id)
+);
+
+INSERT INTO author_papers_new (arxiv_id, author_id, auth)
+  SELECT arxiv_id, author_id, auth FROM author_papers ORDER BY arxiv_id, position;
+
+DROP TABLE author_papers;
+ALTER TABLE author_papers_new RENAME TO author_papers;
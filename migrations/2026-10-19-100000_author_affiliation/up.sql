@@ -0,0 +1,8 @@
+-- Rows were inserted in author order so far
+ALTER TABLE author_papers ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE author_papers SET position = (
+  SELECT count(*) FROM author_papers o
+  WHERE o.arxiv_id = author_papers.arxiv_id AND o.rowid < author_papers.rowid
+);
+
+ALTER TABLE author_papers ADD COLUMN affiliation TEXT;
@@ -0,0 +1,24 @@
+DROP TRIGGER papers_fts_author_delete;
+DROP TRIGGER papers_fts_author_update;
+DROP TRIGGER papers_fts_author_insert;
+DROP TRIGGER papers_fts_delete;
+DROP TRIGGER papers_fts_update;
+DROP TRIGGER papers_fts_insert;
+
+DROP TABLE papers_fts;
+DROP INDEX papers_announced;
+
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE papers_new (
+  arxiv_id TEXT NOT NULL,
+  title    TEXT NOT NULL,
+  abstract TEXT NOT NULL,
+  prim_sub TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id)
+);
+
+INSERT INTO papers_new (arxiv_id, title, abstract, prim_sub)
+  SELECT arxiv_id, title, abstract, prim_sub FROM papers;
+
+DROP TABLE papers;
+ALTER TABLE papers_new RENAME TO papers;
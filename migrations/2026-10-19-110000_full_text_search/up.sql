@@ -0,0 +1,61 @@
+-- Papers inserted before this migration have no announcement time
+ALTER TABLE papers ADD COLUMN announced DATETIME;
+
+CREATE INDEX papers_announced ON papers(announced);
+
+CREATE VIRTUAL TABLE papers_fts USING fts5(
+  arxiv_id UNINDEXED,
+  title,
+  abstract,
+  authors,
+  tokenize = 'unicode61 remove_diacritics 1'
+);
+
+INSERT INTO papers_fts (arxiv_id, title, abstract, authors)
+  SELECT p.arxiv_id,
+         p.title,
+         p.abstract,
+         coalesce((SELECT group_concat(a.auth, ', ')
+                   FROM (SELECT auth FROM author_papers
+                         WHERE arxiv_id = p.arxiv_id ORDER BY position) a), '')
+  FROM papers p;
+
+-- Keep papers_fts in line with the papers and their authors, see `db::search`
+CREATE TRIGGER papers_fts_insert AFTER INSERT ON papers BEGIN
+  INSERT INTO papers_fts (arxiv_id, title, abstract, authors)
+    VALUES (NEW.arxiv_id, NEW.title, NEW.abstract, '');
+END;
+
+CREATE TRIGGER papers_fts_update AFTER UPDATE OF title, abstract ON papers BEGIN
+  UPDATE papers_fts SET title = NEW.title, abstract = NEW.abstract
+    WHERE arxiv_id = NEW.arxiv_id;
+END;
+
+CREATE TRIGGER papers_fts_delete AFTER DELETE ON papers BEGIN
+  DELETE FROM papers_fts WHERE arxiv_id = OLD.arxiv_id;
+END;
+
+-- The authors are listed in order, as in the metadata
+CREATE TRIGGER papers_fts_author_insert AFTER INSERT ON author_papers BEGIN
+  UPDATE papers_fts
+    SET authors = coalesce((SELECT group_concat(a.auth, ', ')
+                            FROM (SELECT auth FROM author_papers
+                                  WHERE arxiv_id = NEW.arxiv_id ORDER BY position) a), '')
+    WHERE arxiv_id = NEW.arxiv_id;
+END;
+
+CREATE TRIGGER papers_fts_author_update AFTER UPDATE OF position, auth ON author_papers BEGIN
+  UPDATE papers_fts
+    SET authors = coalesce((SELECT group_concat(a.auth, ', ')
+                            FROM (SELECT auth FROM author_papers
+                                  WHERE arxiv_id = NEW.arxiv_id ORDER BY position) a), '')
+    WHERE arxiv_id = NEW.arxiv_id;
+END;
+
+CREATE TRIGGER papers_fts_author_delete AFTER DELETE ON author_papers BEGIN
+  UPDATE papers_fts
+    SET authors = coalesce((SELECT group_concat(a.auth, ', ')
+                            FROM (SELECT auth FROM author_papers
+                                  WHERE arxiv_id = OLD.arxiv_id ORDER BY position) a), '')
+    WHERE arxiv_id = OLD.arxiv_id;
+END;
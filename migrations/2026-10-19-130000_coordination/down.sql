@@ -0,0 +1,19 @@
+DROP TABLE claims;
+DROP TABLE instance_subjects;
+DROP TABLE instances;
+
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE pins_new (
+  id       TEXT NOT NULL,
+  ref_id   TEXT,
+  arxiv_id TEXT NOT NULL,
+  pub_date DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY(id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id)
+);
+
+INSERT INTO pins_new (id, ref_id, arxiv_id, pub_date)
+  SELECT id, ref_id, arxiv_id, pub_date FROM pins;
+
+DROP TABLE pins;
+ALTER TABLE pins_new RENAME TO pins;
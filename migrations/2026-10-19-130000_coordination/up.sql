@@ -0,0 +1,23 @@
+CREATE TABLE instances (
+  name      TEXT NOT NULL,
+  heartbeat DATETIME NOT NULL,
+  PRIMARY KEY(name)
+);
+
+CREATE TABLE instance_subjects (
+  instance TEXT NOT NULL,
+  sub      TEXT NOT NULL,
+  PRIMARY KEY(instance, sub),
+  FOREIGN KEY(instance) REFERENCES instances(name) ON DELETE CASCADE
+);
+
+CREATE TABLE claims (
+  arxiv_id   TEXT NOT NULL,
+  instance   TEXT NOT NULL,
+  claimed_at DATETIME NOT NULL,
+  PRIMARY KEY(arxiv_id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+-- Pins posted before this migration belong to no instance
+ALTER TABLE pins ADD COLUMN instance TEXT;
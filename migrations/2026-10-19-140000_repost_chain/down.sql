@@ -0,0 +1,18 @@
+DROP INDEX pins_ref_id;
+
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE pins_new (
+  id       TEXT NOT NULL,
+  ref_id   TEXT,
+  arxiv_id TEXT NOT NULL,
+  pub_date DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  instance TEXT,
+  PRIMARY KEY(id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id)
+);
+
+INSERT INTO pins_new (id, ref_id, arxiv_id, pub_date, instance)
+  SELECT id, ref_id, arxiv_id, pub_date, instance FROM pins;
+
+DROP TABLE pins;
+ALTER TABLE pins_new RENAME TO pins;
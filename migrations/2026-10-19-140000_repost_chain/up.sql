@@ -0,0 +1,4 @@
+-- The subscribed subject for which a pin is posted
+ALTER TABLE pins ADD COLUMN sub TEXT;
+
+CREATE INDEX pins_ref_id ON pins(ref_id);
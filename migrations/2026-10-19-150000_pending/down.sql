@@ -0,0 +1,2 @@
+DROP TABLE pending_revisions;
+DROP TABLE pending;
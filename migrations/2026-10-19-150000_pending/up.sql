@@ -0,0 +1,23 @@
+-- Posts held back while the session of the publisher is invalid
+CREATE TABLE pending (
+  arxiv_id  TEXT NOT NULL,
+  instance  TEXT NOT NULL,
+  sub       TEXT NOT NULL,
+  queued_at TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, instance, sub),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+-- Replacements of published papers waiting to be announced, with the version
+-- last announced, against which they're announced when the queue drains
+CREATE TABLE pending_revisions (
+  arxiv_id  TEXT NOT NULL,
+  instance  TEXT NOT NULL,
+  sub       TEXT NOT NULL,
+  title     TEXT NOT NULL,
+  auth      TEXT NOT NULL,
+  abs       TEXT NOT NULL,
+  queued_at TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, instance),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
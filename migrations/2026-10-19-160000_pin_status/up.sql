@@ -0,0 +1,3 @@
+-- 'visible', 'folded' or 'deleted', as last seen on Zhihu
+ALTER TABLE pins ADD COLUMN status TEXT NOT NULL DEFAULT 'visible';
+ALTER TABLE pins ADD COLUMN synced_at TEXT;
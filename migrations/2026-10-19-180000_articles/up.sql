@@ -0,0 +1,10 @@
+-- Column articles, i.e. the weekly roundups
+CREATE TABLE articles (
+  id         TEXT PRIMARY KEY NOT NULL,
+  instance   TEXT NOT NULL,
+  title      TEXT NOT NULL,
+  since      TEXT NOT NULL,
+  until      TEXT NOT NULL,
+  draft      BOOLEAN NOT NULL,
+  created_at TEXT NOT NULL
+);
@@ -0,0 +1,34 @@
+DROP INDEX papers_announced;
+
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE papers_new (
+  arxiv_id  TEXT NOT NULL,
+  title     TEXT NOT NULL,
+  abstract  TEXT NOT NULL,
+  prim_sub  TEXT NOT NULL,
+  announced DATETIME,
+  PRIMARY KEY(arxiv_id)
+);
+
+INSERT INTO papers_new (arxiv_id, title, abstract, prim_sub, announced)
+  SELECT arxiv_id, title, abstract, prim_sub, announced FROM papers;
+
+DROP TABLE papers;
+ALTER TABLE papers_new RENAME TO papers;
+
+CREATE INDEX papers_announced ON papers(announced);
+
+-- Dropped with the table, see the full_text_search migration
+CREATE TRIGGER papers_fts_insert AFTER INSERT ON papers BEGIN
+  INSERT INTO papers_fts (arxiv_id, title, abstract, authors)
+    VALUES (NEW.arxiv_id, NEW.title, NEW.abstract, '');
+END;
+
+CREATE TRIGGER papers_fts_update AFTER UPDATE OF title, abstract ON papers BEGIN
+  UPDATE papers_fts SET title = NEW.title, abstract = NEW.abstract
+    WHERE arxiv_id = NEW.arxiv_id;
+END;
+
+CREATE TRIGGER papers_fts_delete AFTER DELETE ON papers BEGIN
+  DELETE FROM papers_fts WHERE arxiv_id = OLD.arxiv_id;
+END;
@@ -0,0 +1,2 @@
+-- A short summary of the abstract, see `summary`
+ALTER TABLE papers ADD COLUMN tldr TEXT;
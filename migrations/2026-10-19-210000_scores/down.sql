@@ -0,0 +1,3 @@
+DROP TABLE scores;
+DROP TABLE digest_stats;
+DROP TABLE digests;
@@ -0,0 +1,41 @@
+-- Digests of the papers not posted alone, see `ranking`. A digest is no pin
+-- of its papers, so it's neither reposted nor revised.
+CREATE TABLE digests (
+  id        TEXT NOT NULL,
+  instance  TEXT NOT NULL,
+  sub       TEXT NOT NULL,
+  pub_date  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  status    TEXT NOT NULL DEFAULT 'visible',
+  synced_at TEXT,
+  PRIMARY KEY(id)
+);
+
+-- Engagement of the digests over time, as `pin_stats` for the pins
+CREATE TABLE digest_stats (
+  digest_id   TEXT NOT NULL,
+  recorded_at TEXT NOT NULL,
+  likes       BIGINT NOT NULL,
+  comments    BIGINT NOT NULL,
+  reposts     BIGINT NOT NULL,
+  PRIMARY KEY(digest_id, recorded_at),
+  FOREIGN KEY(digest_id) REFERENCES digests(id) ON DELETE CASCADE
+);
+
+-- Relevance of the papers by subscription, kept for tuning the weights.
+-- `folded_by` is the instance which left the paper for a digest instead of
+-- posting it alone, and `digest` the digest it's in once posted.
+CREATE TABLE scores (
+  arxiv_id   TEXT NOT NULL,
+  sub        TEXT NOT NULL,
+  keywords   DOUBLE NOT NULL,
+  followed   DOUBLE NOT NULL,
+  cross_list DOUBLE NOT NULL,
+  length     DOUBLE NOT NULL,
+  total      DOUBLE NOT NULL,
+  digest     TEXT,
+  scored_at  TEXT NOT NULL,
+  folded_by  TEXT,
+  PRIMARY KEY(arxiv_id, sub),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE,
+  FOREIGN KEY(digest) REFERENCES digests(id)
+);
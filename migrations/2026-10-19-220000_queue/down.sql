@@ -0,0 +1,15 @@
+-- DROP COLUMN is not available before SQLite 3.35.0
+CREATE TABLE pending_new (
+  arxiv_id  TEXT NOT NULL,
+  instance  TEXT NOT NULL,
+  sub       TEXT NOT NULL,
+  queued_at TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, instance, sub),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+INSERT INTO pending_new (arxiv_id, instance, sub, queued_at)
+  SELECT arxiv_id, instance, sub, queued_at FROM pending;
+
+DROP TABLE pending;
+ALTER TABLE pending_new RENAME TO pending;
@@ -0,0 +1,4 @@
+-- Every post goes through the queue now, `position` orders it and can be
+-- changed by `arxiv_bot queue move`. Posts queued before keep their order by
+-- `queued_at`.
+ALTER TABLE pending ADD COLUMN position BIGINT NOT NULL DEFAULT 0;
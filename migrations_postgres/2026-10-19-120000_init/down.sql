@@ -0,0 +1,14 @@
+DROP TRIGGER papers_fts_author ON author_papers;
+DROP FUNCTION papers_fts_author();
+DROP TRIGGER papers_fts_paper ON papers;
+DROP FUNCTION papers_fts_paper();
+DROP FUNCTION papers_fts_refresh(TEXT);
+DROP TABLE papers_fts;
+DROP TABLE pins;
+DROP TABLE update_time;
+DROP TABLE subjects;
+DROP TABLE author_papers;
+DROP TABLE author_rekey;
+DROP TABLE author_variants;
+DROP TABLE authors;
+DROP TABLE papers;
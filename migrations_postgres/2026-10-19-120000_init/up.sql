@@ -0,0 +1,123 @@
+-- The same schema as all migrations in `migrations` applied to SQLite,
+-- times are stored as text in the format of `db::sql_time` for both backends.
+
+CREATE TABLE papers (
+  arxiv_id  TEXT NOT NULL,
+  title     TEXT NOT NULL,
+  abstract  TEXT NOT NULL,
+  prim_sub  TEXT NOT NULL,
+  announced TEXT,
+  PRIMARY KEY(arxiv_id)
+);
+
+CREATE INDEX papers_announced ON papers(announced);
+
+CREATE TABLE authors (
+  id            SERIAL NOT NULL,
+  name          TEXT NOT NULL,
+  norm_name     TEXT NOT NULL,
+  family        TEXT NOT NULL,
+  orcid         TEXT,
+  arxiv_auth_id TEXT,
+  PRIMARY KEY(id),
+  UNIQUE(norm_name)
+);
+
+CREATE INDEX authors_family ON authors(family);
+
+CREATE TABLE author_variants (
+  author_id INTEGER NOT NULL,
+  variant   TEXT NOT NULL,
+  PRIMARY KEY(author_id, variant),
+  FOREIGN KEY(author_id) REFERENCES authors(id) ON DELETE CASCADE
+);
+
+-- Only SQLite databases have backfilled authors to fix, see `db::author::rekey`
+CREATE TABLE author_rekey (
+  author_id INTEGER NOT NULL,
+  PRIMARY KEY(author_id),
+  FOREIGN KEY(author_id) REFERENCES authors(id) ON DELETE CASCADE
+);
+
+CREATE TABLE author_papers (
+  arxiv_id    TEXT NOT NULL,
+  author_id   INTEGER NOT NULL,
+  position    INTEGER NOT NULL,
+  auth        TEXT NOT NULL,
+  affiliation TEXT,
+  PRIMARY KEY(arxiv_id, author_id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE,
+  FOREIGN KEY(author_id) REFERENCES authors(id)
+);
+
+CREATE TABLE subjects (
+  arxiv_id TEXT NOT NULL,
+  sub      TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, sub),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+CREATE TABLE update_time (
+  subject  TEXT NOT NULL,
+  rss_time TEXT NOT NULL,
+  PRIMARY KEY(subject)
+);
+
+CREATE TABLE pins (
+  id       TEXT NOT NULL,
+  ref_id   TEXT,
+  arxiv_id TEXT NOT NULL,
+  pub_date TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+  PRIMARY KEY(id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id)
+);
+
+-- Weighted as title > authors > abstract, see `db::search`
+CREATE TABLE papers_fts (
+  arxiv_id TEXT NOT NULL,
+  document TSVECTOR NOT NULL,
+  PRIMARY KEY(arxiv_id),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
+
+CREATE INDEX papers_fts_document ON papers_fts USING GIN(document);
+
+-- Keep papers_fts in line with the papers and their authors, see `db::search`.
+-- Deleted papers are taken away by the foreign key.
+CREATE FUNCTION papers_fts_refresh(TEXT) RETURNS VOID AS $$
+  DELETE FROM papers_fts WHERE arxiv_id = $1;
+  INSERT INTO papers_fts (arxiv_id, document)
+    SELECT p.arxiv_id,
+           setweight(to_tsvector('english', p.title), 'A')
+           || setweight(to_tsvector('simple', coalesce(
+                (SELECT string_agg(a.auth, ', ' ORDER BY a.position)
+                 FROM author_papers a WHERE a.arxiv_id = $1), '')), 'B')
+           || setweight(to_tsvector('english', p.abstract), 'C')
+    FROM papers p
+    WHERE p.arxiv_id = $1;
+$$ LANGUAGE SQL;
+
+CREATE FUNCTION papers_fts_paper() RETURNS TRIGGER AS $$
+BEGIN
+  PERFORM papers_fts_refresh(NEW.arxiv_id);
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER papers_fts_paper AFTER INSERT OR UPDATE OF title, abstract ON papers
+  FOR EACH ROW EXECUTE PROCEDURE papers_fts_paper();
+
+CREATE FUNCTION papers_fts_author() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP <> 'INSERT' THEN
+    PERFORM papers_fts_refresh(OLD.arxiv_id);
+  END IF;
+  IF TG_OP <> 'DELETE' THEN
+    PERFORM papers_fts_refresh(NEW.arxiv_id);
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER papers_fts_author AFTER INSERT OR UPDATE OR DELETE ON author_papers
+  FOR EACH ROW EXECUTE PROCEDURE papers_fts_author();
@@ -0,0 +1,4 @@
+ALTER TABLE pins DROP COLUMN instance;
+DROP TABLE claims;
+DROP TABLE instance_subjects;
+DROP TABLE instances;
@@ -0,0 +1,2 @@
+DROP INDEX pins_ref_id;
+ALTER TABLE pins DROP COLUMN sub;
@@ -0,0 +1,2 @@
+ALTER TABLE pins DROP COLUMN synced_at;
+ALTER TABLE pins DROP COLUMN status;
@@ -0,0 +1,10 @@
+-- Engagement of the pins over time, one row per sync
+CREATE TABLE pin_stats (
+  pin_id      TEXT NOT NULL,
+  recorded_at TEXT NOT NULL,
+  likes       BIGINT NOT NULL,
+  comments    BIGINT NOT NULL,
+  reposts     BIGINT NOT NULL,
+  PRIMARY KEY(pin_id, recorded_at),
+  FOREIGN KEY(pin_id) REFERENCES pins(id) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+-- Translated titles and abstracts, `origin` is the text translated
+CREATE TABLE translations (
+  arxiv_id      TEXT NOT NULL,
+  field         TEXT NOT NULL,
+  lang          TEXT NOT NULL,
+  origin        TEXT NOT NULL,
+  text          TEXT NOT NULL,
+  translated_at TEXT NOT NULL,
+  PRIMARY KEY(arxiv_id, field, lang),
+  FOREIGN KEY(arxiv_id) REFERENCES papers(arxiv_id) ON DELETE CASCADE
+);
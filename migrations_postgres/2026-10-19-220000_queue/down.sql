@@ -0,0 +1 @@
+ALTER TABLE pending DROP COLUMN position;
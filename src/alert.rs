@@ -0,0 +1,65 @@
+use super::prelude::*;
+use crate::secret;
+
+// [alert]
+// webhook = 'https://example.com/hook'   # POST {"text": "..."} on every alert
+// expiry_days = 3                        # warn when the cookie expires within this many days
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    webhook: Option<String>,
+    expiry_days: Option<i64>,
+}
+
+// Problems the operator has to fix by hand, always logged
+#[derive(Debug)]
+pub struct Alert {
+    webhook: Option<Url>,
+    expiry: Duration,
+    client: Client,
+}
+
+impl Alert {
+    pub fn send(&self, msg: &str) {
+        error!("{}", msg);
+        if let Some(ref url) = self.webhook {
+            let sent = self.client
+                .post(url.clone())
+                .json(&serde_json::json!({ "text": msg }))
+                .send()
+                .map_err(Error::from)
+                .and_then(|mut resp| check_status_code(&mut resp));
+            if let Err(e) = sent {
+                error!("告警发送失败：{}", e);
+            }
+        }
+    }
+
+    // Warn ahead of the expiry of the credentials, returns whether it did
+    pub fn expiry(&self, expires: Option<DateTime<Utc>>) -> bool {
+        match expires {
+            Some(tm) if tm - Utc::now() < self.expiry => {
+                self.send(&format!("知乎Cookie将于{}过期，请及时更新", tm.with_timezone(&chrono::Local)));
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+pub fn init(conf: &AlertConfig) -> Fallible<Alert> {
+    let webhook = conf.webhook
+        .as_ref()
+        .map(|url| {
+            // The url of a webhook usually carries a token
+            secret::register(url);
+            Url::parse(url).map_err(|_| err_msg("无效的webhook地址"))
+        })
+        .transpose()?;
+
+    Ok(Alert {
+        webhook,
+        expiry: Duration::days(conf.expiry_days.unwrap_or(3)),
+        client: Client::new(),
+    })
+}
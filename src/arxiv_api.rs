@@ -1,12 +1,14 @@
 use super::prelude::*;
 
 pub use index::Index;
+pub use author::Author;
 pub use metadata::MetaData;
 
 pub use subject::Subject;
 // pub type Subject = String; // TODO: Type Rich Enum
 
 mod index;
+mod author;
 mod subject;
 mod metadata;
 pub mod fetch;
@@ -0,0 +1,123 @@
+use super::*;
+
+// Lower-cased name particles which belong to the family name, e.g. "van der Waals"
+const PARTICLES: &[&str] = &["van", "von", "der", "den", "de", "del", "della", "di", "da", "du", "la", "le"];
+
+// A parsed author name, arXiv writes the same person in many ways:
+// "John Smith", "J. Smith", "Smith, John" or "Jöhn Smith"
+#[derive(Clone, Debug, PartialEq)]
+pub struct Author {
+    given: Vec<String>,
+    family: String,
+}
+
+impl FromStr for Author {
+    type Err = Error;
+    fn from_str(name: &str) -> Fallible<Self> {
+        let tokens = |s: &str| -> Vec<String> {
+            deunicode::deunicode(s)
+                .split(|c: char| c.is_whitespace() || c == '.')
+                .map(|t| t.trim_matches(|c: char| !c.is_alphanumeric() && c != '-').to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect()
+        };
+
+        // "Smith, John"
+        let (given, family) = if let Some(ix) = name.find(',') {
+            (tokens(&name[ix + 1..]), tokens(&name[..ix]))
+        } else {
+            let mut given = tokens(name);
+            let mut family = given.pop().into_iter().collect::<Vec<_>>();
+            while given.len() > 1 && PARTICLES.contains(&given[given.len() - 1].as_str()) {
+                family.insert(0, given.pop().unwrap()); // safely unwrap, checked length
+            }
+            (given, family)
+        };
+
+        ensure!(!family.is_empty(), "Invalid author name: {}", name);
+        Ok(Author { given, family: family.join(" ") })
+    }
+}
+
+impl Author {
+    // Take the whole name as the family name if it isn't one, so that an odd
+    // name in the metadata still gets an identity
+    pub fn lenient(name: &str) -> Author {
+        Author::from_str(name).unwrap_or_else(|_| Author {
+            given: Vec::new(),
+            family: name.trim().to_lowercase(),
+        })
+    }
+
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    // The normalised form used as the identity key, "John Smith" => "john smith"
+    pub fn key(&self) -> String {
+        let mut key = self.given.join(" ");
+        if !key.is_empty() {
+            key.push(' ');
+        }
+        key.push_str(&self.family);
+        key
+    }
+
+    // Whether two names may refer to the same person, i.e. the family names are
+    // equal and every given name is equal to or abbreviated by the other one
+    pub fn matches(&self, other: &Author) -> bool {
+        self.family == other.family && self.given
+            .iter()
+            .zip(other.given.iter())
+            .all(|(a, b)| {
+                a == b || (a.len() == 1 && b.starts_with(a.as_str())) || (b.len() == 1 && a.starts_with(b.as_str()))
+            })
+    }
+}
+
+impl fmt::Display for Author {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(name: &str) -> Author {
+        Author::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(author("John Smith").key(), "john smith");
+        assert_eq!(author("Smith, John").key(), "john smith");
+        assert_eq!(author("Jöhn  Smith").key(), "john smith");
+        assert_eq!(author("J. R. R. Tolkien").key(), "j r r tolkien");
+        assert_eq!(author("Jan van der Waals").family(), "van der waals");
+        assert_eq!(author("Plato").key(), "plato");
+        assert!(Author::from_str("").is_err());
+        assert!(Author::from_str("Smith, ").is_ok());
+        assert!(Author::from_str(", John").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let john = author("John Smith");
+        assert!(john.matches(&author("J. Smith")));
+        assert!(author("J. Smith").matches(&john));
+        assert!(john.matches(&author("Smith, John")));
+        assert!(john.matches(&author("Jöhn Smith")));
+        assert!(john.matches(&author("Smith")));
+        assert!(!john.matches(&author("Jane Smith")));
+        assert!(!john.matches(&author("John Smyth")));
+        assert!(!author("J. Smith").matches(&author("K. Smith")));
+    }
+
+    #[test]
+    fn lenient() {
+        assert_eq!(Author::lenient("Smith, John"), author("John Smith"));
+        assert_eq!(Author::lenient(" ??? ").key(), "???");
+    }
+}
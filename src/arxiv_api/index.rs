@@ -61,16 +61,16 @@ impl FromStr for Index {
 
 // All Results are safely unwrapped, the inputs are totally predictable
 // because every part of the url are formatted by the current module
-impl Into<Url> for &Index {
-    fn into(self) -> Url {
+impl From<&Index> for Url {
+    fn from(idx: &Index) -> Url {
         let path = Url::parse("https://arxiv.org/abs/").unwrap();
-        match self {
+        match idx {
             // TODO: formatting the subject
             // If there's no slash, it'll be dropped when join another location on it
             Index::Old { ref sub, .. } => path.join(&format!("{}/", sub.as_str())).unwrap(),
             Index::New {..} => path,
         }
-            .join(&self.to_string())
+            .join(&idx.to_string())
             .unwrap()
     }
 }
@@ -79,9 +79,9 @@ impl fmt::Display for Index {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Index::Old { date, idx, .. } =>
-                write!(f, "{}{:03}", date.format("%y%m").to_string(), idx),
+                write!(f, "{}{:03}", date.format("%y%m"), idx),
             Index::New { date, idx } =>
-                write!(f, "{}.{:05}", date.format("%y%m").to_string(), idx),
+                write!(f, "{}.{:05}", date.format("%y%m"), idx),
         }
     }
 }
@@ -9,6 +9,8 @@ pub struct MetaData {
     pub index: Index,
     pub title: String,
     pub auth: Vec<String>,
+    // One entry for each author, in the same order
+    pub affil: Vec<Option<String>>,
     pub abs: String,
     pub sub: Vec<Subject>,
     pub prim_sub: Subject,
@@ -65,7 +67,7 @@ impl TryFrom<Index> for MetaData {
         let auth = document
             .find(Child(Class("authors"), Name("a")))
             .map(|n| n.text())
-            .collect();
+            .collect::<Vec<_>>();
 
         // <blockquote class="abstract mathjax">
         //   <span class="descriptor">
@@ -112,6 +114,42 @@ impl TryFrom<Index> for MetaData {
             .text()
             .as_str())?;
 
-        Ok(MetaData { index, title, auth, abs, sub, prim_sub })
+        let affil = affiliations(&index, auth.len()).unwrap_or_else(|e| {
+            warn!("获取{}的作者单位失败：{}", index, e);
+            vec![None; auth.len()]
+        });
+
+        Ok(MetaData { index, title, auth, affil, abs, sub, prim_sub })
     }
 }
+
+// The abstract pages don't show affiliations, only the Atom API does
+//
+// <entry>
+//   <author>
+//     <name>{author_1}</name>
+//     <arxiv:affiliation>{affiliation_1}</arxiv:affiliation>
+//   </author>
+//   ...
+// </entry>
+fn affiliations(index: &Index, len: usize) -> Fallible<Vec<Option<String>>> {
+    let url: Url = index.into();
+    let mut resp = reqwest::get(&format!(
+        "http://export.arxiv.org/api/query?id_list={}",
+        url.path().trim_start_matches("/abs/")
+    ))?;
+
+    check_status_code(&mut resp)?;
+
+    let affil = Document::from_read(resp)?
+        .find(Name("author"))
+        .map(|n| {
+            n.find(Name("arxiv:affiliation"))
+                .next()
+                .map(|a| a.text().trim().to_owned())
+        })
+        .collect::<Vec<_>>();
+
+    ensure!(affil.len() == len, "Author list of the Atom API mismatches the abstract page");
+    Ok(affil)
+}
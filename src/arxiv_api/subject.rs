@@ -51,7 +51,7 @@ impl Subject {
     }
 
     fn strip(s: &str) -> &str {
-        s.split(|c| c == '(' || c == ')').collect::<Vec<_>>()[1]
+        s.split(['(', ')']).collect::<Vec<_>>()[1]
     }
 }
 
@@ -0,0 +1,333 @@
+use super::prelude::*;
+use crate::{
+    db::DbConfig,
+    log::LogConfig,
+    timer::TimerConfig,
+    coord::CoordConfig,
+    publisher::{Auth, Tags},
+    alert::AlertConfig,
+    pins::SyncConfig,
+    roundup::RoundupConfig,
+    figure::Images,
+    translate::TranslateConfig,
+    summary::SummaryConfig,
+    ranking::RankingConfig,
+    queue::QueueConfig,
+};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use toml::value::Table;
+
+// Secrets can be given by environment variables instead of the config file
+const OVERRIDES: &[(&str, &str, &str)] = &[
+    ("ARXIV_BOT_AUTH_COOKIE", "auth", "cookie"),
+    ("ARXIV_BOT_DATABASE_URL", "db", "database_url"),
+];
+// Items whose values never show up in the log
+const SECRET_KEYS: &[&str] = &["auth", "db.database_url", "alert.webhook", "translate.api_key", "summary.api_key"];
+
+// The whole config file, see `arxiv_bot.toml.example`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub db: DbConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub timer: TimerConfig,
+    #[serde(default)]
+    pub auth: Auth,
+    #[serde(default)]
+    pub tags: Tags,
+    #[serde(default)]
+    pub images: Images,
+    pub scraper: Scraper,
+    pub coord: Option<CoordConfig>,
+    #[serde(default)]
+    pub revision: Threshold,
+    #[serde(default)]
+    pub reload: Reload,
+    #[serde(default)]
+    pub alert: AlertConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub roundup: RoundupConfig,
+    #[serde(default)]
+    pub translate: TranslateConfig,
+    #[serde(default)]
+    pub summary: SummaryConfig,
+    #[serde(default)]
+    pub ranking: RankingConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+    // The file with the overrides applied, for `diff`
+    #[serde(skip)]
+    raw: Table,
+}
+
+// [reload]
+// watch = true   # reload once the config file is modified, besides SIGHUP
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Reload {
+    #[serde(default)]
+    pub watch: bool,
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "第{}行：{}", line, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Fallible<Self> {
+        Config::parse(text).map_err(|problems| err_msg(problems
+            .iter()
+            .map(Problem::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")))
+    }
+}
+
+impl Config {
+    // Parse and check the config, reporting every problem found instead of the first
+    // one. The cookie is read from its file and `pdftoppm` is probed if images are
+    // enabled, so that `config check` finds them missing, but nothing is connected.
+    pub fn parse(text: &str) -> Result<Config, Vec<Problem>> {
+        parse(text)
+    }
+
+    pub fn schema() -> Fallible<String> {
+        Ok(serde_json::to_string_pretty(&schemars::schema_for!(Config))?)
+    }
+
+    // Changed items from this config, e.g. `timer.interval: 08:00:00 -> 04:00:00`
+    pub fn diff(&self, new: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+        walk("", Some(&toml::Value::Table(self.raw.clone())), Some(&toml::Value::Table(new.raw.clone())), &mut changes);
+        changes
+    }
+}
+
+fn parse(text: &str) -> Result<Config, Vec<Problem>> {
+    let mut raw = toml::from_str::<Table>(text).map_err(|e| vec![Problem {
+        line: None, // the message has the position already
+        msg: e.to_string(),
+    }])?;
+
+    for (var, sec, key) in OVERRIDES {
+        if let Ok(val) = std::env::var(var) {
+            let sec = raw
+                .entry(sec.to_string())
+                .or_insert_with(|| toml::Value::Table(Table::new()));
+            if let toml::Value::Table(t) = sec {
+                t.insert(key.to_string(), toml::Value::String(val));
+            }
+        }
+    }
+
+    // The cookie may come from elsewhere, see `publisher::Auth`
+    raw.entry("auth".to_owned()).or_insert_with(|| toml::Value::Table(Table::new()));
+
+    // The items are taken from the schema, so that a new one is known here as well
+    let items = schemars::schema_for!(Config).schema.object.unwrap_or_default();
+    let mut problems = Vec::new();
+    for key in raw.keys().filter(|k| !items.properties.contains_key(*k)) {
+        problems.push(Problem {
+            line: line_of(text, &[key]),
+            msg: format!("未知的配置项`{}`", key),
+        });
+    }
+    for key in items.required.iter().filter(|k| !raw.contains_key(*k)) {
+        problems.push(Problem { line: None, msg: format!("缺少`[{}]`项", key) });
+    }
+
+    // Each item is checked on its own so that one mistake doesn't hide the others
+    let mut check = |key: &str, res: Fallible<()>| if let Err(e) = res {
+        problems.push(problem(text, key, e.to_string()));
+    };
+    if let Some(db) = section::<DbConfig>(&raw, "db", &mut check) {
+        check("db", db.check());
+    }
+    if let Some(log) = section::<LogConfig>(&raw, "log", &mut check) {
+        check("log", log.check());
+    }
+    if let Some(tm) = section::<TimerConfig>(&raw, "timer", &mut check) {
+        check("timer", crate::timer::init(&tm).map(drop));
+    }
+    if let Some(auth) = section::<Auth>(&raw, "auth", &mut check) {
+        check("auth", auth.check());
+    }
+    if let Some(tags) = section::<Tags>(&raw, "tags", &mut check) {
+        check("tags", tags.check());
+    }
+    if let Some(images) = section::<Images>(&raw, "images", &mut check) {
+        check("images", images.check());
+    }
+    if let Some(scraper) = section::<Scraper>(&raw, "scraper", &mut check) {
+        check("scraper", scraper.check());
+    }
+    if let Some(cc) = section::<CoordConfig>(&raw, "coord", &mut check) {
+        check("coord", crate::coord::init(Some(&cc)).map(drop));
+    }
+    section::<Threshold>(&raw, "revision", &mut check);
+    section::<Reload>(&raw, "reload", &mut check);
+    if let Some(alert) = section::<AlertConfig>(&raw, "alert", &mut check) {
+        check("alert", crate::alert::init(&alert).map(drop));
+    }
+    if let Some(sync) = section::<SyncConfig>(&raw, "sync", &mut check) {
+        check("sync", crate::pins::init(&sync).map(drop));
+    }
+    if let Some(roundup) = section::<RoundupConfig>(&raw, "roundup", &mut check) {
+        check("roundup", crate::roundup::init(&roundup).map(drop));
+    }
+    if let Some(tr) = section::<TranslateConfig>(&raw, "translate", &mut check) {
+        check("translate", crate::translate::init(&tr).map(drop));
+    }
+    if let Some(sm) = section::<SummaryConfig>(&raw, "summary", &mut check) {
+        check("summary", crate::summary::init(&sm).map(drop));
+    }
+    if let Some(rk) = section::<RankingConfig>(&raw, "ranking", &mut check) {
+        check("ranking", crate::ranking::init(&rk).map(drop));
+    }
+    if let Some(qu) = section::<QueueConfig>(&raw, "queue", &mut check) {
+        check("queue", crate::queue::init(&qu).map(drop));
+    }
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    let mut conf = from_value::<Config>(&toml::Value::Table(raw.clone()))
+        .map_err(|e| vec![Problem { line: None, msg: e.to_string() }])?;
+    conf.raw = raw;
+    Ok(conf)
+}
+
+fn section<T: DeserializeOwned>(raw: &Table, key: &str, check: &mut dyn FnMut(&str, Fallible<()>)) -> Option<T> {
+    match from_value::<T>(raw.get(key)?) {
+        Ok(t) => Some(t),
+        Err(e) => {
+            check(key, Err(e));
+            None
+        },
+    }
+}
+
+// `Value::try_into` gives datetimes as strings, from which the times of the
+// config can't be read, so a table is read again from its text. Positions in
+// that text are of no use, see `problem` instead.
+fn from_value<T: DeserializeOwned>(val: &toml::Value) -> Fallible<T> {
+    if !val.is_table() {
+        return Ok(val.clone().try_into::<T>()?);
+    }
+    toml::from_str::<T>(&toml::to_string(val)?).map_err(|e| {
+        let msg = e.to_string();
+        err_msg(match msg.find(" at line ") {
+            Some(i) => msg[..i].to_owned(),
+            None => msg,
+        })
+    })
+}
+
+// Point at the field named in the message if any, otherwise at the item
+fn problem(text: &str, key: &str, msg: String) -> Problem {
+    let field = Regex::new(r"(?:field|for key) `([\w.]+)`")
+        .unwrap() // safely unwrap
+        .captures(&msg)
+        .map(|c| c[1].to_owned());
+    let line = field
+        .and_then(|f| line_of(text, &[key, &f]))
+        .or_else(|| line_of(text, &[key]));
+    Problem { line, msg: format!("`{}`项：{}", key, msg) }
+}
+
+// Line number of a table or a key, found by scanning the text since the parsed
+// values carry no positions
+fn line_of(text: &str, path: &[&str]) -> Option<usize> {
+    let path = path.join(".");
+    let mut table = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') {
+            table = line.trim_matches(|c| c == '[' || c == ']').trim().to_owned();
+            if table == path || table.starts_with(&format!("{}.", path)) {
+                return Some(i + 1);
+            }
+        } else if let Some(key) = line.split('=').next().filter(|_| line.contains('=')) {
+            let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+            let full = if table.is_empty() {
+                key.to_owned()
+            } else {
+                format!("{}.{}", table, key)
+            };
+            if full == path {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+fn walk(key: &str, old: Option<&toml::Value>, new: Option<&toml::Value>, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Some(toml::Value::Table(o)), Some(toml::Value::Table(n))) => {
+            let keys = o.keys().chain(n.keys()).collect::<BTreeSet<_>>();
+            for k in keys {
+                let path = if key.is_empty() {
+                    k.to_owned()
+                } else {
+                    format!("{}.{}", key, k)
+                };
+                walk(&path, o.get(k), n.get(k), changes);
+            }
+        },
+        (o, n) if o == n => (),
+        (o, n) => {
+            let secret = SECRET_KEYS
+                .iter()
+                .any(|s| key == *s || key.starts_with(&format!("{}.", s)));
+            let show = |v: Option<&toml::Value>| match v {
+                None => "（无）".to_owned(),
+                Some(_) if secret => "******".to_owned(),
+                Some(v) => v.to_string(),
+            };
+            changes.push(format!("{}: {} -> {}", key, show(o), show(n)));
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let conf = include_str!("../arxiv_bot.toml.example").parse::<Config>();
+        assert!(conf.is_ok(), "{:?}", conf.err());
+    }
+
+    #[test]
+    fn problems() {
+        let text = "[db]\ndatabase_path = 'a.db'\n\n[timer]\ninterval = 'often'\n\n[auth]\ncookie = 'z_c0=1'\n\n\
+            [scraper]\nsubject = ['math.LO']\n\n[log.console]\nlevel = 'info'\n\n[unknown]\n";
+        let problems = Config::parse(text).unwrap_err();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().any(|p| p.line == Some(5) && p.msg.starts_with("`timer`项")));
+        assert!(problems.iter().any(|p| p.line == Some(16)));
+        // No position in the text read again
+        assert!(problems.iter().all(|p| !p.msg.contains(" at line ")));
+    }
+}
@@ -0,0 +1,141 @@
+use super::prelude::*;
+use crate::db::instance;
+
+// Instances without heartbeat for this long are considered gone
+const EXPIRE_DAYS: i64 = 7;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CoordConfig {
+    instance: String,
+    #[schemars(with = "Option<String>")]
+    wait: Option<toml::value::Datetime>,
+}
+
+// Instances sharing one database coordinate through it: the instance subscribing
+// the primary subject of a paper posts it, and the others repost that pin.
+// Without the `coord` item every paper is posted by the instance itself.
+#[derive(Debug)]
+pub struct Coord {
+    name: String,
+    enabled: bool,
+    wait: std::time::Duration,
+}
+
+#[derive(Debug)]
+pub enum Role {
+    // Claimed the paper, post the original pin
+    Owner,
+    // Wait for the owner and repost its pin
+    Follower,
+    // The original has been posted, e.g. the paper is cross-listed
+    // in another subject of this instance, repost it
+    Repost(Pin),
+    // Already posted by this instance for the subject
+    Done,
+}
+
+impl Coord {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn register(&self, conn: SqlConn, subs: &[Subject]) -> Fallible<()> {
+        if self.enabled {
+            instance::register(conn, &self.name, subs)?;
+        }
+        Ok(())
+    }
+
+    // The role of this instance on a paper from the feed of a subscribed subject
+    pub fn role(&self, conn: SqlConn, md: &MetaData, sub: &Subject) -> Fallible<Role> {
+        if pin::posted_for(conn, &md.index, &self.name, sub)? {
+            return Ok(Role::Done);
+        }
+        // The digest isn't reposted, nor is the paper posted again
+        if score::is_folded(conn, &md.index)? {
+            return Ok(Role::Done);
+        }
+        if let Some(orig) = pin::original(conn, &md.index)? {
+            return Ok(Role::Repost(orig));
+        }
+        if !self.enabled {
+            return Ok(Role::Owner);
+        }
+
+        let owners = instance::owners(conn, &md.prim_sub, expiry())?;
+
+        // Nobody subscribes the primary subject, the first one claiming it posts it
+        let owned = owners.is_empty() || owners[0] == self.name;
+        if owned && instance::claim(conn, &md.index, &self.name)? {
+            Ok(Role::Owner)
+        } else {
+            Ok(Role::Follower)
+        }
+    }
+
+    // Claim the paper after the owner failed to post it in time
+    pub fn claim(&self, conn: SqlConn, md: &MetaData) -> Fallible<bool> {
+        if self.enabled {
+            instance::claim(conn, &md.index, &self.name)
+        } else {
+            Ok(true)
+        }
+    }
+
+    // Whether a follower should post the paper itself after all, since the owner
+    // failed to: nobody has claimed it `wait` after it was queued here, or the
+    // owner holding the claim is gone and claimed it more than `wait` ago. A
+    // live owner keeps the claim however long its queue is.
+    pub fn take_over(&self, conn: SqlConn, md: &MetaData, queued: DateTime<Utc>) -> Fallible<bool> {
+        if !self.enabled {
+            return Ok(true);
+        }
+
+        match instance::claimant(conn, &md.index)? {
+            Some((ref owner, _)) if *owner == self.name => Ok(true),
+            Some((owner, at)) => {
+                if !self.waited(at) || instance::alive(conn, &owner, expiry())? {
+                    return Ok(false);
+                }
+                warn!("{}的认领实例{}已失联，改由本实例发布", md.index, owner);
+                instance::transfer(conn, &md.index, &owner, &self.name)
+            },
+            None if self.waited(queued) => {
+                warn!("等待{}的原始想法超时，改由本实例发布", md.index);
+                instance::claim(conn, &md.index, &self.name)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn waited(&self, since: DateTime<Utc>) -> bool {
+        match (Utc::now() - since).to_std() {
+            Ok(d) => d >= self.wait,
+            Err(_) => false,
+        }
+    }
+}
+
+// Heartbeats older than this are from instances which are gone
+fn expiry() -> DateTime<FixedOffset> {
+    Utc::now().fixed_offset() - Duration::days(EXPIRE_DAYS)
+}
+
+pub fn init(cc: Option<&CoordConfig>) -> Fallible<Coord> {
+    if let Some(cc) = cc {
+        Ok(Coord {
+            name: cc.instance.clone(),
+            enabled: true,
+            wait: cc.wait
+                .as_ref()
+                .map_or(Ok(std::time::Duration::from_secs(30 * 60)), toml_duration)?,
+        })
+    } else {
+        Ok(Coord {
+            name: "default".to_owned(),
+            enabled: false,
+            wait: std::time::Duration::from_secs(0),
+        })
+    }
+}
@@ -1,22 +1,90 @@
 use super::schema::{
     pins as p,
     papers as pr,
+    claims as cl,
+    instances as ins,
+    instance_subjects as isb,
     authors as at,
+    author_papers as ap,
+    author_variants as av,
+    author_rekey as ark,
     subjects as sb,
     update_time as ut,
+    pending as pd,
+    pending_revisions as prv,
+    pin_stats as ps,
+    articles as ar,
+    translations as tl,
+    scores as sc,
+    digests as dg,
+    digest_stats as ds,
 };
 use super::prelude::*;
 use diesel::prelude::*;
 use diesel::dsl::*;
 
-const DB_KEY: &str = "db";
+#[cfg(feature = "postgres")]
+use diesel::pg::PgConnection;
 
-pub type SqlConn<'a> = &'a SqliteConnection;
+// Run the same diesel query on whichever backend the connection is, the body
+// is expanded once per backend so that diesel can check it against each of them
+macro_rules! dispatch {
+    ($conn:expr, $c:ident => $body:expr) => {
+        match $conn {
+            Conn::Sqlite($c) => $body,
+            #[cfg(feature = "postgres")]
+            Conn::Pg($c) => $body,
+        }
+    };
+}
+
+pub enum Conn {
+    Sqlite(SqliteConnection),
+    #[cfg(feature = "postgres")]
+    Pg(PgConnection),
+}
+
+pub type SqlConn<'a> = &'a Conn;
 type MDList = Vec<MetaData>;
 
-#[derive(Deserialize, Debug)]
-struct DbConfig {
-    database_path: String,
+// `database_url` can be given by `ARXIV_BOT_DATABASE_URL` instead, see `config`
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DbConfig {
+    // 'sqlite' (default) or 'postgres'
+    backend: Option<String>,
+    database_path: Option<String>,
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    database_url: Option<String>,
+}
+
+enum Target<'a> {
+    Sqlite(&'a str),
+    #[cfg(feature = "postgres")]
+    Pg(&'a str),
+}
+
+impl DbConfig {
+    fn target(&self) -> Fallible<Target<'_>> {
+        let backend = self.backend.as_ref().map_or("sqlite", String::as_str);
+        match backend {
+            "sqlite" => Ok(Target::Sqlite(self.database_path
+                .as_ref()
+                .ok_or_else(|| err_msg("SQLite后端必须指定`database_path`"))?)),
+            #[cfg(feature = "postgres")]
+            "postgres" => Ok(Target::Pg(self.database_url
+                .as_ref()
+                .ok_or_else(|| err_msg("PostgreSQL后端必须指定`database_url`"))?)),
+            #[cfg(not(feature = "postgres"))]
+            "postgres" => bail!("当前版本编译时未启用PostgreSQL支持，请使用`--features postgres`重新编译"),
+            _ => bail!("无效的数据库后端：{}", backend)
+        }
+    }
+
+    // Check the config without connecting
+    pub fn check(&self) -> Fallible<()> {
+        self.target().map(drop)
+    }
 }
 
 #[derive(Queryable, Insertable, Debug)]
@@ -26,6 +94,8 @@ struct Paper {
     title: String,
     abstract_: String,
     prim_sub: String,
+    announced: Option<String>,
+    tldr: Option<String>,
 }
 
 impl From<MetaData> for Paper
@@ -36,6 +106,8 @@ impl From<MetaData> for Paper
             title: md.title,
             abstract_: md.abs,
             prim_sub: md.prim_sub.as_str().to_owned(),
+            announced: None,
+            tldr: None,
         }
     }
 }
@@ -43,56 +115,135 @@ impl From<MetaData> for Paper
 impl<'a> TryFrom<(SqlConn<'a>, Paper)> for MetaData {
     type Error = Error;
     fn try_from((conn, p): (SqlConn, Paper)) -> Fallible<Self> {
-        use at::dsl::{
-            *,
-            arxiv_id as aid
-        };
-        use sb::dsl::{
-            *,
-            arxiv_id as sid
-        };
-
-        Ok(MetaData {
-            title: p.title,
-            abs: p.abstract_,
-            prim_sub: Subject::from_str(&p.prim_sub)?,
-            index: Index::from_str(&p.arxiv_id)?,
-            sub: subjects
-                .select(sub)
-                .filter(sid.eq(p.arxiv_id.clone()))
-                .load::<String>(conn)?
-                .into_iter()
-                .map(|s| Subject::from_str(&s))
-                .try_fold(Vec::new(), try_fold_helper)?,
-            auth: authors
-                .select(auth)
-                .filter(aid.eq(p.arxiv_id))
-                .load::<String>(conn)?,
-        })
+        // safely unwrap, one paper in and one out
+        Ok(query::assemble(conn, vec![p])?.pop().unwrap())
     }
 }
 
-pub fn init(env: &Env) -> Fallible<SqliteConnection> {
-    let dbc: DbConfig = toml::from_str(env
-        .get(DB_KEY)
-        .ok_or_else(|| err_msg("必须指定一个数据库路径"))?
-        .to_string()
-        .as_str())?;
+// The same format as `CURRENT_TIMESTAMP`, so that times can be compared in SQL
+fn sql_time<Tz: TimeZone>(tm: &DateTime<Tz>) -> String {
+    tm.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// The inverse of `sql_time`
+fn parse_time(tm: &str) -> Fallible<DateTime<Utc>> {
+    Ok(NaiveDateTime::parse_from_str(tm, "%Y-%m-%d %H:%M:%S")?.and_utc())
+}
+
+// Run `f` in a transaction, the functions taking the connection can be used in
+// it. A nested one becomes a savepoint.
+fn transaction<T>(conn: SqlConn, f: impl FnOnce() -> Fallible<T>) -> Fallible<T> {
+    dispatch!(conn, c => c.transaction::<_, Error, _>(f))
+}
+
+// Whether another instance has inserted the same row meanwhile
+fn conflict(e: &Error) -> bool {
+    use diesel::result::{Error as DieselError, DatabaseErrorKind};
 
-    let conn = SqliteConnection::establish(dbc.database_path.as_str())?;
-    conn.execute("PRAGMA foreign_keys = ON;")?;
+    matches!(
+        e.downcast_ref::<DieselError>(),
+        Some(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _))
+    )
+}
+
+pub fn init(dbc: &DbConfig) -> Fallible<Conn> {
+    let conn = match dbc.target()? {
+        Target::Sqlite(path) => {
+            let conn = SqliteConnection::establish(path)?;
+            conn.execute("PRAGMA foreign_keys = ON;")?;
+            Conn::Sqlite(conn)
+        },
+        #[cfg(feature = "postgres")]
+        Target::Pg(url) => {
+            // The password is in the url, and so may be in the errors
+            crate::secret::register(url);
+            Conn::Pg(PgConnection::establish(url)?)
+        },
+    };
+    author::rekey(&conn)?;
     Ok(conn)
 }
 
+mod query;
+
 pub mod paper {
     use super::*;
 
     pub fn insert(conn: SqlConn, subj: Subject, md: MetaData, tm: DateTime<FixedOffset>) -> Fallible<()> {
         use ut::dsl::*;
         use pr::dsl::papers;
-        use at::dsl::{
+
+        transaction(conn, || {
+            // Instances sharing a database or subscribing multiple subjects see the same paper
+            if !exists(conn, &md.index)? {
+                // Need partially moved data types
+                let paper = Paper {
+                    announced: Some(sql_time(&tm)),
+                    ..Paper::from(md.clone())
+                };
+                // Another instance may insert it in the meantime, which stores
+                // the details as well
+                let inserted = transaction(conn, || {
+                    Ok(dispatch!(conn, c => insert_into(papers).values(paper).execute(c))?)
+                });
+                match inserted {
+                    Ok(_) => insert_details(conn, md)?,
+                    Err(ref e) if conflict(e) => (),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if let Some(t) = last(conn, &subj)? {
+                if tm > t {
+                    dispatch!(conn, c => diesel::update(update_time.find(subj.as_str()))
+                        .set(rss_time.eq(tm.to_rfc3339()))
+                        .execute(c))?;
+                }
+            } else {
+                let row = (subject.eq(subj.as_str()), rss_time.eq(tm.to_rfc3339()));
+                dispatch!(conn, c => insert_into(update_time).values(row).execute(c))?;
+            }
+            Ok(())
+        })
+    }
+
+    // Replace the stored metadata with a new version of the paper
+    pub fn update(conn: SqlConn, md: MetaData) -> Fallible<()> {
+        use pr::dsl::*;
+
+        let idx = md.index.to_string();
+        transaction(conn, || {
+            dispatch!(conn, c => diesel::update(papers.find(&idx))
+                .set((
+                    title.eq(&md.title),
+                    abstract_.eq(&md.abs),
+                    prim_sub.eq(md.prim_sub.as_str()),
+                    // Made again from the new abstract, see `summary`
+                    tldr.eq(None::<String>),
+                ))
+                .execute(c))?;
+
+            {
+                use ap::dsl::*;
+                dispatch!(conn, c => delete(author_papers.filter(arxiv_id.eq(&idx))).execute(c))?;
+            }
+            {
+                use sb::dsl::*;
+                dispatch!(conn, c => delete(subjects.filter(arxiv_id.eq(&idx))).execute(c))?;
+            }
+            insert_details(conn, md)
+        })
+    }
+
+    // Authors and subjects of a stored paper, the full-text index follows them
+    // by triggers, see the full_text_search migration
+    fn insert_details(conn: SqlConn, md: MetaData) -> Fallible<()> {
+        use ap::dsl::{
             auth,
-            authors,
+            position,
+            author_id,
+            affiliation,
+            author_papers,
             arxiv_id as aid,
         };
         use sb::dsl::{
@@ -101,108 +252,98 @@ pub mod paper {
             arxiv_id as sid,
         };
 
-        // Need partially moved data types
-        insert_into(papers)
-            .values(Paper::from(md.clone()))
-            .execute(conn)?;
+        let mut seen = Vec::new();
+        for (pos, (at, af)) in md.auth.into_iter().zip(md.affil).enumerate() {
+            let aut = author::upsert(conn, &at)?;
+            // The same person listed twice, keep the first position
+            if seen.contains(&aut) {
+                continue;
+            }
+            seen.push(aut);
 
-        for at in md.auth {
-            insert_into(authors)
-                .values((aid.eq(md.index.to_string()), auth.eq(at)))
-                .execute(conn)?;
+            let row = (
+                aid.eq(md.index.to_string()),
+                author_id.eq(aut),
+                position.eq(pos as i32),
+                auth.eq(at),
+                affiliation.eq(af),
+            );
+            dispatch!(conn, c => insert_into(author_papers).values(row).execute(c))?;
         }
 
         for sb in md.sub {
-            insert_into(subjects)
-                .values((sid.eq(md.index.to_string()), sub.eq(sb.as_str())))
-                .execute(conn)?;
-        }
-
-        if let Some(t) = last(conn, &subj)? {
-            if tm > t {
-                update(update_time.find(subj.as_str()))
-                    .set(rss_time.eq(tm.to_rfc3339()))
-                    .execute(conn)?;
-            }
-        } else {
-            insert_into(update_time)
-                .values((subject.eq(subj.as_str()), rss_time.eq(tm.to_rfc3339())))
-                .execute(conn)?;
+            let row = (sid.eq(md.index.to_string()), sub.eq(sb.as_str()));
+            dispatch!(conn, c => insert_into(subjects).values(row).execute(c))?;
         }
         Ok(())
     }
 
-    pub fn by_id(conn: SqlConn, idx: Index) -> Fallible<MetaData> {
+    pub use super::search::{search, Filter};
+    pub use super::query::{Query, by_ids};
+
+    pub fn exists(conn: SqlConn, idx: &Index) -> Fallible<bool> {
         use pr::dsl::*;
-        let p = papers
+
+        Ok(dispatch!(conn, c => papers
             .find(idx.to_string())
-            .first::<Paper>(conn)?;
-        MetaData::try_from((conn, p))
+            .select(arxiv_id)
+            .first::<String>(c)
+            .optional())?
+            .is_some())
     }
 
-    pub fn by_subs(conn: SqlConn, subs: Vec<Subject>) -> Fallible<MDList> {
+    // The stored summary, unless it's of another abstract
+    pub fn summary(conn: SqlConn, idx: &Index, abs: &str) -> Fallible<Option<String>> {
         use pr::dsl::*;
-        use sb::dsl::{
-            sub,
-            subjects,
-        };
 
-        papers
-            .inner_join(subjects)
-            .filter(sub.eq_any(
-                subs.iter()
-                    .map(Subject::as_str)
-                    .collect::<Vec<_>>()
-            ))
-            .select(arxiv_id)
-            .distinct()
-            .load::<String>(conn)?
-            .into_iter()
-            .map(|ix| {
-                by_id(conn, Index::from_str(&ix)?)
-            })
-            .try_fold(Vec::new(), try_fold_helper)
+        Ok(dispatch!(conn, c => papers
+            .find(idx.to_string())
+            .select((abstract_, tldr))
+            .first::<(String, Option<String>)>(c)
+            .optional())?
+            .filter(|(a, _)| a == abs)
+            .and_then(|(_, t)| t))
     }
 
-    pub fn by_prim_sub(conn: SqlConn, sub: Subject) -> Fallible<MDList> {
+    // Kept only if the stored abstract is the summarised one
+    pub fn set_summary(conn: SqlConn, idx: &Index, abs: &str, text: &str) -> Fallible<()> {
         use pr::dsl::*;
 
-        papers
-            .filter(prim_sub.eq(sub.as_str()))
-            .load::<Paper>(conn)?
-            .into_iter()
-            .map(|p| MetaData::try_from((conn, p)))
-            .try_fold(Vec::new(), try_fold_helper)
+        dispatch!(conn, c => diesel::update(papers.find(idx.to_string()).filter(abstract_.eq(abs)))
+            .set(tldr.eq(text))
+            .execute(c))?;
+        Ok(())
     }
 
-    pub fn by_auths(conn: SqlConn, auths: Vec<String>) -> Fallible<MDList> {
+    pub fn by_id(conn: SqlConn, idx: Index) -> Fallible<MetaData> {
         use pr::dsl::*;
-        use at::dsl::{
-            auth,
-            authors,
-        };
+        let p = dispatch!(conn, c => papers
+            .find(idx.to_string())
+            .first::<Paper>(c))?;
+        MetaData::try_from((conn, p))
+    }
 
-        papers
-            .inner_join(authors)
-            .filter(auth.eq_any(auths))
-            .select(arxiv_id)
-            .distinct()
-            .load::<String>(conn)?
-            .into_iter()
-            .map(|ix| {
-                by_id(conn, Index::from_str(&ix)?)
-            })
-            .try_fold(Vec::new(), try_fold_helper)
+    pub fn by_subs(conn: SqlConn, subs: Vec<Subject>) -> Fallible<MDList> {
+        Query::new().subs(subs).load(conn)
+    }
+
+    pub fn by_prim_sub(conn: SqlConn, sub: Subject) -> Fallible<MDList> {
+        Query::new().prim_sub(sub).load(conn)
+    }
+
+    // Authors are matched fuzzily, "J. Smith" finds the papers of "John Smith"
+    pub fn by_auths(conn: SqlConn, auths: Vec<String>) -> Fallible<MDList> {
+        Query::new().auths(auths).load(conn)
     }
 
     pub fn last(conn: SqlConn, sub: &Subject) -> Fallible<Option<DateTime<FixedOffset>>> {
         use ut::dsl::*;
 
-        Ok(update_time
+        Ok(dispatch!(conn, c => update_time
             .select(rss_time)
             .filter(subject.eq(sub.as_str()))
             .distinct()
-            .load::<String>(conn)?
+            .load::<String>(c))?
             .into_iter()
             .map(|tm| {
                 DateTime::parse_from_rfc3339(&tm)
@@ -225,39 +366,1148 @@ pub mod paper {
     }
 }
 
+mod search {
+    use super::*;
+    use diesel::sql_query;
+    use diesel::sql_types::{Text, Nullable, BigInt};
+
+    // Filters applied on top of the full-text query, empty fields match everything
+    #[derive(Debug, Default)]
+    pub struct Filter {
+        pub subs: Vec<Subject>,
+        pub since: Option<DateTime<FixedOffset>>,
+        pub until: Option<DateTime<FixedOffset>>,
+    }
+
+    #[derive(QueryableByName)]
+    struct Hit {
+        #[sql_type = "Text"]
+        arxiv_id: String,
+    }
+
+    // The query uses the FTS5 syntax in SQLite, e.g. `"forcing axiom" AND title:large`,
+    // and the websearch syntax in PostgreSQL, e.g. `"forcing axiom" -large`.
+    // Results are ranked by relevance in which a title hit weighs the most
+    pub fn search(conn: SqlConn, query: &str, filter: &Filter, limit: usize) -> Fallible<MDList> {
+        // Bind the subject list as one string to keep the number of parameters fixed
+        let subs = filter.subs
+            .iter()
+            .map(|s| format!(",{},", s.as_str()))
+            .collect::<String>();
+        let since = filter.since.as_ref().map(sql_time);
+        let until = filter.until.as_ref().map(sql_time);
+
+        let hits = match conn {
+            Conn::Sqlite(c) => sql_query("SELECT f.arxiv_id AS arxiv_id \
+                FROM papers_fts f JOIN papers p ON p.arxiv_id = f.arxiv_id \
+                WHERE papers_fts MATCH ? \
+                  AND (? IS NULL OR p.announced >= ?) \
+                  AND (? IS NULL OR p.announced < ?) \
+                  AND (? = '' OR EXISTS ( \
+                    SELECT 1 FROM subjects s \
+                    WHERE s.arxiv_id = f.arxiv_id AND instr(?, ',' || s.sub || ',') > 0)) \
+                ORDER BY bm25(papers_fts, 0.0, 10.0, 1.0, 5.0) \
+                LIMIT ?")
+                .bind::<Text, _>(query)
+                .bind::<Nullable<Text>, _>(&since)
+                .bind::<Nullable<Text>, _>(&since)
+                .bind::<Nullable<Text>, _>(&until)
+                .bind::<Nullable<Text>, _>(&until)
+                .bind::<Text, _>(&subs)
+                .bind::<Text, _>(&subs)
+                .bind::<BigInt, _>(limit as i64)
+                .load::<Hit>(c)?,
+            #[cfg(feature = "postgres")]
+            Conn::Pg(c) => sql_query("SELECT f.arxiv_id AS arxiv_id \
+                FROM papers_fts f JOIN papers p ON p.arxiv_id = f.arxiv_id \
+                WHERE f.document @@ websearch_to_tsquery('english', $1) \
+                  AND ($2::text IS NULL OR p.announced >= $2) \
+                  AND ($3::text IS NULL OR p.announced < $3) \
+                  AND ($4 = '' OR EXISTS ( \
+                    SELECT 1 FROM subjects s \
+                    WHERE s.arxiv_id = f.arxiv_id AND strpos($4, ',' || s.sub || ',') > 0)) \
+                ORDER BY ts_rank(f.document, websearch_to_tsquery('english', $1)) DESC \
+                LIMIT $5")
+                .bind::<Text, _>(query)
+                .bind::<Nullable<Text>, _>(&since)
+                .bind::<Nullable<Text>, _>(&until)
+                .bind::<Text, _>(&subs)
+                .bind::<BigInt, _>(limit as i64)
+                .load::<Hit>(c)?,
+        };
+
+        let ids = hits
+            .into_iter()
+            .map(|h| h.arxiv_id)
+            .collect::<Vec<_>>();
+
+        paper::by_ids(conn, &ids)
+    }
+}
+
+// A row of `pins`
+#[derive(Queryable, Debug)]
+pub struct PinRow {
+    pub id: String,
+    pub ref_id: Option<String>,
+    pub arxiv_id: String,
+    pub pub_date: String,
+    pub instance: Option<String>,
+    pub sub: Option<String>,
+    pub status: String,
+    pub synced_at: Option<String>,
+}
+
 pub mod pin {
     use super::*;
 
-    pub fn insert(conn: SqlConn, pin: Pin, r#ref: Option<Pin>, idx: Index) -> Fallible<()> {
+    // `sub` is the subscribed subject for which the pin is posted
+    pub fn insert(conn: SqlConn, pin: Pin, r#ref: Option<Pin>, idx: Index, inst: &str, subj: &Subject) -> Fallible<()> {
         use p::dsl::*;
 
-        if let Some(rid) = r#ref {
-            insert_into(pins)
-                .values((
-                    id.eq(pin.id),
-                    ref_id.eq(rid.id),
-                    arxiv_id.eq(idx.to_string())
-                ))
-                .execute(conn)?;
-        } else {
-            insert_into(pins)
-                .values((
-                    id.eq(pin.id),
-                    arxiv_id.eq(idx.to_string())
-                ))
-                .execute(conn)?;
-        }
+        let row = (
+            id.eq(pin.id),
+            ref_id.eq(r#ref.map(|rid| rid.id)),
+            arxiv_id.eq(idx.to_string()),
+            instance.eq(inst),
+            sub.eq(subj.as_str()),
+        );
+        dispatch!(conn, c => insert_into(pins).values(row).execute(c))?;
 
         Ok(())
     }
 
+    // The original pin of a paper, reposts refer to it
     pub fn by_arxiv_id(conn: SqlConn, idx: Index) -> Fallible<Pin> {
+        original(conn, &idx)?.ok_or_else(|| err_msg(format!("{}尚未发布", idx)))
+    }
+
+    pub fn original(conn: SqlConn, idx: &Index) -> Fallible<Option<Pin>> {
+        use p::dsl::*;
+
+        Ok(dispatch!(conn, c => pins
+            .select(id)
+            .filter(arxiv_id.eq(idx.to_string()))
+            .filter(ref_id.is_null())
+            .filter(status.ne(PinStatus::Deleted.as_str()))
+            .first::<String>(c)
+            .optional())?
+            .map(|i| Pin::from(i.as_str())))
+    }
+
+    // Whether the instance has posted or reposted the paper for the subject
+    pub fn posted_for(conn: SqlConn, idx: &Index, inst: &str, subj: &Subject) -> Fallible<bool> {
+        use p::dsl::*;
+
+        Ok(dispatch!(conn, c => pins
+            .select(id)
+            .filter(arxiv_id.eq(idx.to_string()))
+            .filter(instance.eq(inst))
+            .filter(sub.eq(subj.as_str()))
+            .first::<String>(c)
+            .optional())?
+            .is_some())
+    }
+
+    // Pins reposting the given one, in the order they're posted
+    pub fn reposts(conn: SqlConn, pin: &Pin) -> Fallible<Vec<Pin>> {
+        use p::dsl::*;
+
+        Ok(dispatch!(conn, c => pins
+            .select(id)
+            .filter(ref_id.eq(&pin.id))
+            .order(pub_date.asc())
+            .load::<String>(c))?
+            .iter()
+            .map(|i| Pin::from(i.as_str()))
+            .collect())
+    }
+
+    // All pins of a paper as (pin, the pin it reposts), the original comes first
+    pub fn chain(conn: SqlConn, idx: &Index) -> Fallible<Vec<(Pin, Option<Pin>)>> {
+        use p::dsl::*;
+
+        Ok(dispatch!(conn, c => pins
+            .select((id, ref_id))
+            .filter(arxiv_id.eq(idx.to_string()))
+            .order((ref_id.is_not_null(), pub_date.asc()))
+            .load::<(String, Option<String>)>(c))?
+            .iter()
+            .map(|(i, r)| (Pin::from(i.as_str()), r.as_ref().map(|r| Pin::from(r.as_str()))))
+            .collect())
+    }
+
+    // All rows of a paper, the original comes first
+    pub fn rows(conn: SqlConn, idx: &Index) -> Fallible<Vec<PinRow>> {
+        use p::dsl::*;
+
+        Ok(dispatch!(conn, c => pins
+            .filter(arxiv_id.eq(idx.to_string()))
+            .order((ref_id.is_not_null(), pub_date.asc()))
+            .load::<PinRow>(c))?)
+    }
+
+    pub fn set_status(conn: SqlConn, pin: &Pin, st: PinStatus) -> Fallible<()> {
+        use p::dsl::*;
+
+        dispatch!(conn, c => diesel::update(pins.find(&pin.id))
+            .set((status.eq(st.as_str()), synced_at.eq(sql_time(&Utc::now()))))
+            .execute(c))?;
+        Ok(())
+    }
+
+    // When the instance posted last, for the pace of the queue. Digests count
+    // as well.
+    pub fn last_posted(conn: SqlConn, inst: &str) -> Fallible<Option<DateTime<Utc>>> {
+        use p::dsl::*;
+
+        let pinned = dispatch!(conn, c => pins
+            .select(max(pub_date))
+            .filter(instance.eq(inst))
+            .first::<Option<String>>(c))?;
+        let digested = {
+            use dg::dsl::*;
+            dispatch!(conn, c => digests
+                .select(max(pub_date))
+                .filter(instance.eq(inst))
+                .first::<Option<String>>(c))?
+        };
+        // Times in the same format compare as text
+        pinned.max(digested)
+            .map(|tm| parse_time(&tm))
+            .transpose()
+    }
+
+    // Papers the instance has posted, reposted or put in a digest in [since,
+    // until), which are not deleted, in the order they're posted
+    pub fn published(
+        conn: SqlConn,
+        inst: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Fallible<Vec<Index>> {
+        use p::dsl::*;
+
+        let mut rows = dispatch!(conn, c => pins
+            .select((arxiv_id, pub_date))
+            .filter(instance.eq(inst))
+            .filter(pub_date.ge(sql_time(&since)))
+            .filter(pub_date.lt(sql_time(&until)))
+            .filter(status.ne(PinStatus::Deleted.as_str()))
+            .load::<(String, String)>(c))?;
+        rows.extend(digest::papers(conn, inst, since, until)?);
+        rows.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut seen = Vec::new();
+        for (ix, _) in rows {
+            if !seen.contains(&ix) {
+                seen.push(ix);
+            }
+        }
+        seen.iter()
+            .map(|ix| Index::from_str(ix))
+            .try_fold(Vec::new(), try_fold_helper)
+    }
+
+    // Pins of the instance posted since `since` which are not deleted and
+    // haven't been synced since `before`, the longest unsynced first
+    pub fn stale(
+        conn: SqlConn,
+        inst: &str,
+        since: DateTime<Utc>,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Fallible<Vec<Pin>> {
         use p::dsl::*;
 
-        Ok(Pin::from(pins
+        let ids = dispatch!(conn, c => pins
             .select(id)
+            .filter(instance.eq(inst))
+            .filter(pub_date.ge(sql_time(&since)))
+            .filter(status.ne(PinStatus::Deleted.as_str()))
+            .filter(synced_at.is_null().or(synced_at.lt(sql_time(&before))))
+            .order((synced_at.is_not_null(), synced_at.asc()))
+            .limit(limit)
+            .load::<String>(c))?;
+        Ok(ids.iter().map(|i| Pin::from(i.as_str())).collect())
+    }
+}
+
+// Digests of the papers not posted alone, see `ranking`. They aren't pins of
+// their papers, which refer to them by `scores.digest`.
+pub mod digest {
+    use super::*;
+
+    pub fn insert(conn: SqlConn, pin: &Pin, inst: &str, subj: &Subject) -> Fallible<()> {
+        use dg::dsl::*;
+
+        let row = (
+            id.eq(&pin.id),
+            instance.eq(inst),
+            sub.eq(subj.as_str()),
+        );
+        dispatch!(conn, c => insert_into(digests).values(row).execute(c))?;
+        Ok(())
+    }
+
+    pub fn set_status(conn: SqlConn, pin: &Pin, st: PinStatus) -> Fallible<()> {
+        use dg::dsl::*;
+
+        dispatch!(conn, c => diesel::update(digests.find(&pin.id))
+            .set((status.eq(st.as_str()), synced_at.eq(sql_time(&Utc::now()))))
+            .execute(c))?;
+        Ok(())
+    }
+
+    // As `pin::stale` for the digests
+    pub fn stale(
+        conn: SqlConn,
+        inst: &str,
+        since: DateTime<Utc>,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Fallible<Vec<Pin>> {
+        use dg::dsl::*;
+
+        let ids = dispatch!(conn, c => digests
+            .select(id)
+            .filter(instance.eq(inst))
+            .filter(pub_date.ge(sql_time(&since)))
+            .filter(status.ne(PinStatus::Deleted.as_str()))
+            .filter(synced_at.is_null().or(synced_at.lt(sql_time(&before))))
+            .order((synced_at.is_not_null(), synced_at.asc()))
+            .limit(limit)
+            .load::<String>(c))?;
+        Ok(ids.iter().map(|i| Pin::from(i.as_str())).collect())
+    }
+
+    // Papers in the digests of the instance posted in [since, until) which are
+    // not deleted, with the time they're posted
+    pub fn papers(
+        conn: SqlConn,
+        inst: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Fallible<Vec<(String, String)>> {
+        use dg::dsl::*;
+
+        let posted = dispatch!(conn, c => digests
+            .select((id, pub_date))
+            .filter(instance.eq(inst))
+            .filter(pub_date.ge(sql_time(&since)))
+            .filter(pub_date.lt(sql_time(&until)))
+            .filter(status.ne(PinStatus::Deleted.as_str()))
+            .load::<(String, String)>(c))?;
+        let ids = posted.iter().map(|(i, _)| i).collect::<Vec<_>>();
+
+        use sc::dsl::{scores, arxiv_id, digest};
+        Ok(dispatch!(conn, c => scores
+            .select((arxiv_id, digest))
+            .filter(digest.eq_any(&ids))
+            .load::<(String, Option<String>)>(c))?
+            .into_iter()
+            .filter_map(|(ix, dig)| {
+                let tm = posted.iter().find(|(i, _)| Some(i) == dig.as_ref())?;
+                Some((ix, tm.1.clone()))
+            })
+            .collect())
+    }
+}
+
+// Instances sharing one database, see `coord`
+pub mod instance {
+    use super::*;
+    use diesel::result::{
+        Error as DieselError,
+        DatabaseErrorKind,
+    };
+
+    // Register the instance with its subjects and refresh its heartbeat
+    pub fn register(conn: SqlConn, inst: &str, subs: &[Subject]) -> Fallible<()> {
+        {
+            use ins::dsl::*;
+
+            let tm = sql_time(&Utc::now());
+            let known = dispatch!(conn, c => instances
+                .find(inst)
+                .select(name)
+                .first::<String>(c)
+                .optional())?;
+
+            if known.is_some() {
+                dispatch!(conn, c => update(instances.find(inst))
+                    .set(heartbeat.eq(&tm))
+                    .execute(c))?;
+            } else {
+                let row = (name.eq(inst), heartbeat.eq(&tm));
+                dispatch!(conn, c => insert_into(instances).values(row).execute(c))?;
+            }
+        }
+
+        use isb::dsl::*;
+        dispatch!(conn, c => delete(instance_subjects.filter(instance.eq(inst))).execute(c))?;
+        for s in subs {
+            let row = (instance.eq(inst), sub.eq(s.as_str()));
+            dispatch!(conn, c => insert_into(instance_subjects).values(row).execute(c))?;
+        }
+        Ok(())
+    }
+
+    // Instances subscribing the subject whose heartbeat is newer than `since`, sorted by name
+    pub fn owners(conn: SqlConn, subj: &Subject, since: DateTime<FixedOffset>) -> Fallible<Vec<String>> {
+        use isb::dsl::*;
+        use ins::dsl::{
+            name,
+            heartbeat,
+            instances,
+        };
+
+        Ok(dispatch!(conn, c => instance_subjects
+            .inner_join(instances)
+            .select(name)
+            .filter(sub.eq(subj.as_str()))
+            .filter(heartbeat.ge(sql_time(&since)))
+            .order(name.asc())
+            .load::<String>(c))?)
+    }
+
+    // Claim the original post of a paper, false if another instance has claimed it
+    pub fn claim(conn: SqlConn, idx: &Index, inst: &str) -> Fallible<bool> {
+        use cl::dsl::*;
+
+        let owner = dispatch!(conn, c => claims
+            .find(idx.to_string())
+            .select(instance)
+            .first::<String>(c)
+            .optional())?;
+        if let Some(owner) = owner {
+            return Ok(owner == inst);
+        }
+
+        let row = (
+            arxiv_id.eq(idx.to_string()),
+            instance.eq(inst),
+            claimed_at.eq(sql_time(&Utc::now())),
+        );
+        // Another instance may insert the same claim in the meantime
+        match dispatch!(conn, c => insert_into(claims).values(row).execute(c)) {
+            Ok(_) => Ok(true),
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // The instance holding the claim on a paper and when it claimed it
+    pub fn claimant(conn: SqlConn, idx: &Index) -> Fallible<Option<(String, DateTime<Utc>)>> {
+        use cl::dsl::*;
+
+        dispatch!(conn, c => claims
+            .find(idx.to_string())
+            .select((instance, claimed_at))
+            .first::<(String, String)>(c)
+            .optional())?
+            .map(|(inst, tm)| -> Fallible<(String, DateTime<Utc>)> {
+                Ok((inst, parse_time(&tm)?))
+            })
+            .transpose()
+    }
+
+    // Whether the heartbeat of the instance is newer than `since`
+    pub fn alive(conn: SqlConn, inst: &str, since: DateTime<FixedOffset>) -> Fallible<bool> {
+        use ins::dsl::*;
+
+        Ok(dispatch!(conn, c => instances
+            .filter(name.eq(inst))
+            .filter(heartbeat.ge(sql_time(&since)))
+            .count()
+            .get_result::<i64>(c))? > 0)
+    }
+
+    // Move the claim from an instance to another, false if it has changed meanwhile
+    pub fn transfer(conn: SqlConn, idx: &Index, from: &str, to: &str) -> Fallible<bool> {
+        use cl::dsl::*;
+
+        let moved = dispatch!(conn, c => update(claims
+            .filter(arxiv_id.eq(idx.to_string()))
+            .filter(instance.eq(from)))
+            .set((instance.eq(to), claimed_at.eq(sql_time(&Utc::now()))))
+            .execute(c))?;
+        Ok(moved > 0)
+    }
+}
+
+// Engagement of the pins over time, see `pins::Pins::sync`
+pub mod stats {
+    use super::*;
+
+    pub fn record(conn: SqlConn, pin: &Pin, state: &PinState) -> Fallible<()> {
+        use ps::dsl::*;
+
+        let row = (
+            pin_id.eq(&pin.id),
+            recorded_at.eq(sql_time(&Utc::now())),
+            likes.eq(state.likes),
+            comments.eq(state.comments),
+            reposts.eq(state.reposts),
+        );
+        dispatch!(conn, c => insert_into(pin_stats).values(row).execute(c))?;
+        Ok(())
+    }
+
+    pub fn record_digest(conn: SqlConn, pin: &Pin, state: &PinState) -> Fallible<()> {
+        use ds::dsl::*;
+
+        let row = (
+            digest_id.eq(&pin.id),
+            recorded_at.eq(sql_time(&Utc::now())),
+            likes.eq(state.likes),
+            comments.eq(state.comments),
+            reposts.eq(state.reposts),
+        );
+        dispatch!(conn, c => insert_into(digest_stats).values(row).execute(c))?;
+        Ok(())
+    }
+
+    // (arxiv id, pin, likes, comments, reposts)
+    pub type Engagement = (String, String, i64, i64, i64);
+
+    // The latest engagement of every pin posted in [since, until), digests
+    // aren't pins of their papers and so not among them
+    pub fn latest(
+        conn: SqlConn,
+        since: Option<DateTime<FixedOffset>>,
+        until: Option<DateTime<FixedOffset>>,
+    ) -> Fallible<Vec<Engagement>> {
+        use ps::dsl::*;
+        use p::dsl::{pins, arxiv_id, pub_date};
+
+        let rows = dispatch!(conn, c => {
+            let mut query = pin_stats
+                .inner_join(pins)
+                .select((arxiv_id, pin_id, recorded_at, likes, comments, reposts))
+                .into_boxed();
+            if let Some(ref tm) = since {
+                query = query.filter(pub_date.ge(sql_time(tm)));
+            }
+            if let Some(ref tm) = until {
+                query = query.filter(pub_date.lt(sql_time(tm)));
+            }
+            query
+                .order((pin_id.asc(), recorded_at.desc()))
+                .load::<(String, String, String, i64, i64, i64)>(c)
+        })?;
+
+        // Rows of a pin are adjacent with the latest first
+        let mut latest: Vec<Engagement> = Vec::new();
+        for (ix, pid, _, l, c, r) in rows {
+            if latest.last().is_none_or(|last| last.1 != pid) {
+                latest.push((ix, pid, l, c, r));
+            }
+        }
+        Ok(latest)
+    }
+
+    // As `latest` for the digests, with their subject in place of the paper
+    pub fn digests(
+        conn: SqlConn,
+        since: Option<DateTime<FixedOffset>>,
+        until: Option<DateTime<FixedOffset>>,
+    ) -> Fallible<Vec<Engagement>> {
+        use ds::dsl::*;
+        use dg::dsl::{digests, sub, pub_date};
+
+        let rows = dispatch!(conn, c => {
+            let mut query = digest_stats
+                .inner_join(digests)
+                .select((sub, digest_id, recorded_at, likes, comments, reposts))
+                .into_boxed();
+            if let Some(ref tm) = since {
+                query = query.filter(pub_date.ge(sql_time(tm)));
+            }
+            if let Some(ref tm) = until {
+                query = query.filter(pub_date.lt(sql_time(tm)));
+            }
+            query
+                .order((digest_id.asc(), recorded_at.desc()))
+                .load::<(String, String, String, i64, i64, i64)>(c)
+        })?;
+
+        let mut latest: Vec<Engagement> = Vec::new();
+        for (s, pid, _, l, c, r) in rows {
+            if latest.last().is_none_or(|last| last.1 != pid) {
+                latest.push((s, pid, l, c, r));
+            }
+        }
+        Ok(latest)
+    }
+}
+
+// Column articles of the instances, see `roundup`
+pub mod article {
+    use super::*;
+
+    pub fn insert(
+        conn: SqlConn,
+        art: &Article,
+        inst: &str,
+        name: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        is_draft: bool,
+    ) -> Fallible<()> {
+        use ar::dsl::*;
+
+        let row = (
+            id.eq(&art.id),
+            instance.eq(inst),
+            title.eq(name),
+            since.eq(sql_time(&from)),
+            until.eq(sql_time(&to)),
+            draft.eq(is_draft),
+            created_at.eq(sql_time(&Utc::now())),
+        );
+        dispatch!(conn, c => insert_into(articles).values(row).execute(c))?;
+        Ok(())
+    }
+
+    // The end of the period covered by the latest article of the instance
+    pub fn last(conn: SqlConn, inst: &str) -> Fallible<Option<DateTime<Utc>>> {
+        use ar::dsl::*;
+
+        dispatch!(conn, c => articles
+            .select(until)
+            .filter(instance.eq(inst))
+            .order(until.desc())
+            .first::<String>(c)
+            .optional())?
+            .map(|tm| parse_time(&tm))
+            .transpose()
+    }
+}
+
+// Relevance of the papers, see `ranking`
+pub mod score {
+    use super::*;
+
+    pub fn insert(conn: SqlConn, idx: &Index, subj: &Subject, s: &Score) -> Fallible<()> {
+        use sc::dsl::*;
+
+        let row = (
+            arxiv_id.eq(idx.to_string()),
+            sub.eq(subj.as_str()),
+            keywords.eq(s.keywords),
+            followed.eq(s.followed),
+            cross_list.eq(s.cross_list),
+            length.eq(s.length),
+            total.eq(s.total),
+            scored_at.eq(sql_time(&Utc::now())),
+        );
+        transaction(conn, || {
+            dispatch!(conn, c => delete(scores.find((idx.to_string(), subj.as_str()))).execute(c))?;
+            dispatch!(conn, c => insert_into(scores).values(row).execute(c))?;
+            Ok(())
+        })
+    }
+
+    // The paper is left for the next digest of the instance instead of posted alone
+    pub fn fold(conn: SqlConn, idx: &Index, subj: &Subject, inst: &str) -> Fallible<()> {
+        use sc::dsl::*;
+
+        dispatch!(conn, c => diesel::update(scores.find((idx.to_string(), subj.as_str())))
+            .set(folded_by.eq(inst))
+            .execute(c))?;
+        Ok(())
+    }
+
+    // Papers the instance has folded which aren't in a digest yet, the best first
+    pub fn folded(conn: SqlConn, inst: &str) -> Fallible<Vec<(Index, Subject)>> {
+        use sc::dsl::*;
+
+        dispatch!(conn, c => scores
+            .select((arxiv_id, sub))
+            .filter(folded_by.eq(inst))
+            .filter(digest.is_null())
+            .order((total.desc(), scored_at.asc()))
+            .load::<(String, String)>(c))?
+            .iter()
+            .map(|(ix, s)| -> Fallible<(Index, Subject)> {
+                Ok((Index::from_str(ix)?, Subject::from_str(s)?))
+            })
+            .try_fold(Vec::new(), try_fold_helper)
+    }
+
+    // The paper is folded into the digest pin
+    pub fn set_digest(conn: SqlConn, idx: &Index, subj: &Subject, pin: &Pin) -> Fallible<()> {
+        use sc::dsl::*;
+
+        dispatch!(conn, c => diesel::update(scores.find((idx.to_string(), subj.as_str())))
+            .set(digest.eq(&pin.id))
+            .execute(c))?;
+        Ok(())
+    }
+
+    // Whether an instance has left the paper for a digest, under any subject
+    pub fn is_folded(conn: SqlConn, idx: &Index) -> Fallible<bool> {
+        use sc::dsl::*;
+
+        Ok(dispatch!(conn, c => scores
+            .select(arxiv_id)
+            .filter(arxiv_id.eq(idx.to_string()))
+            .filter(folded_by.is_not_null())
+            .first::<String>(c)
+            .optional())?
+            .is_some())
+    }
+
+    // The digest the paper is in, if posted
+    pub fn digest_of(conn: SqlConn, idx: &Index) -> Fallible<Option<Pin>> {
+        use sc::dsl::*;
+
+        Ok(dispatch!(conn, c => scores
+            .select(digest)
+            .filter(arxiv_id.eq(idx.to_string()))
+            .filter(digest.is_not_null())
+            .first::<Option<String>>(c)
+            .optional())?
+            .and_then(|d| d)
+            .map(|d| Pin::from(d.as_str())))
+    }
+}
+
+// Translations of the papers, see `translate`
+pub mod translation {
+    use super::*;
+
+    // The cached translation, unless it's of another text
+    pub fn get(conn: SqlConn, idx: &Index, name: &str, lg: &str, orig: &str) -> Fallible<Option<String>> {
+        use tl::dsl::*;
+
+        Ok(dispatch!(conn, c => translations
+            .find((idx.to_string(), name, lg))
+            .select((origin, text))
+            .first::<(String, String)>(c)
+            .optional())?
+            .filter(|(o, _)| o == orig)
+            .map(|(_, t)| t))
+    }
+
+    pub fn put(conn: SqlConn, idx: &Index, name: &str, lg: &str, orig: &str, txt: &str) -> Fallible<()> {
+        use tl::dsl::*;
+
+        let row = (
+            arxiv_id.eq(idx.to_string()),
+            field.eq(name),
+            lang.eq(lg),
+            origin.eq(orig),
+            text.eq(txt),
+            translated_at.eq(sql_time(&Utc::now())),
+        );
+        transaction(conn, || {
+            dispatch!(conn, c => delete(translations.find((idx.to_string(), name, lg))).execute(c))?;
+            dispatch!(conn, c => insert_into(translations).values(row).execute(c))?;
+            Ok(())
+        })
+    }
+}
+
+// The outbound queue, every post goes through it, see `queue`. The revisions
+// wait in a table of their own, the digests as folded scores.
+pub mod pending {
+    use super::*;
+
+    // Queue at the end unless queued already
+    pub fn push(conn: SqlConn, idx: &Index, inst: &str, subj: &Subject) -> Fallible<()> {
+        use pd::dsl::*;
+
+        // Read and write at once, so that another push or `queue move` doesn't
+        // come in between
+        transaction(conn, || {
+            let queued = dispatch!(conn, c => pending
+                .find((idx.to_string(), inst, subj.as_str()))
+                .count()
+                .get_result::<i64>(c))? > 0;
+            if !queued {
+                let last = dispatch!(conn, c => pending
+                    .select(max(position))
+                    .filter(instance.eq(inst))
+                    .first::<Option<i64>>(c))?;
+                let row = (
+                    arxiv_id.eq(idx.to_string()),
+                    instance.eq(inst),
+                    sub.eq(subj.as_str()),
+                    queued_at.eq(sql_time(&Utc::now())),
+                    position.eq(last.map_or(0, |p| p + 1)),
+                );
+                dispatch!(conn, c => insert_into(pending).values(row).execute(c))?;
+            }
+            Ok(())
+        })
+    }
+
+    // Queued posts of the instance in order, with the time they're queued
+    pub fn list(conn: SqlConn, inst: &str) -> Fallible<Vec<(Index, Subject, DateTime<Utc>)>> {
+        use pd::dsl::*;
+
+        dispatch!(conn, c => pending
+            .select((arxiv_id, sub, queued_at))
+            .filter(instance.eq(inst))
+            .order((position.asc(), queued_at.asc(), arxiv_id.asc()))
+            .load::<(String, String, String)>(c))?
+            .iter()
+            .map(|(ix, s, tm)| -> Fallible<(Index, Subject, DateTime<Utc>)> {
+                Ok((Index::from_str(ix)?, Subject::from_str(s)?, parse_time(tm)?))
+            })
+            .try_fold(Vec::new(), try_fold_helper)
+    }
+
+    pub fn remove(conn: SqlConn, idx: &Index, inst: &str, subj: &Subject) -> Fallible<()> {
+        use pd::dsl::*;
+
+        dispatch!(conn, c => delete(pending.find((idx.to_string(), inst, subj.as_str()))).execute(c))?;
+        Ok(())
+    }
+
+    // Remove the paper for every subject, returns the number of posts removed
+    pub fn remove_paper(conn: SqlConn, idx: &Index, inst: &str) -> Fallible<usize> {
+        use pd::dsl::*;
+
+        Ok(dispatch!(conn, c => delete(pending
             .filter(arxiv_id.eq(idx.to_string()))
-            .first::<String>(conn)?
-            .as_str()))
+            .filter(instance.eq(inst)))
+            .execute(c))?)
+    }
+
+    pub fn clear(conn: SqlConn, inst: &str) -> Fallible<usize> {
+        use pd::dsl::*;
+
+        Ok(dispatch!(conn, c => delete(pending.filter(instance.eq(inst))).execute(c))?)
+    }
+
+    // Move the paper to the position (from 0) in the queue, its posts for
+    // several subjects stay together
+    pub fn move_to(conn: SqlConn, idx: &Index, inst: &str, to: usize) -> Fallible<()> {
+        use pd::dsl::*;
+
+        let ix = idx.to_string();
+        transaction(conn, || {
+            let (mut moved, mut rows): (Vec<_>, Vec<_>) = list(conn, inst)?
+                .into_iter()
+                .map(|(i, s, _)| (i.to_string(), s))
+                .partition(|(i, _)| *i == ix);
+            ensure!(!moved.is_empty(), "{}不在队列中", idx);
+
+            let to = to.min(rows.len());
+            let tail = rows.split_off(to);
+            rows.append(&mut moved);
+            rows.extend(tail);
+
+            for (pos, (i, s)) in rows.iter().enumerate() {
+                dispatch!(conn, c => diesel::update(pending.find((i, inst, s.as_str())))
+                    .set(position.eq(pos as i64))
+                    .execute(c))?;
+            }
+            Ok(())
+        })
+    }
+
+    // Queue the revision of a published paper, against the version last
+    // announced. A paper queued already keeps the version it's queued against.
+    pub fn hold_revision(conn: SqlConn, prev: &MetaData, inst: &str, subj: &Subject) -> Fallible<()> {
+        use prv::dsl::*;
+
+        let row = (
+            arxiv_id.eq(prev.index.to_string()),
+            instance.eq(inst),
+            sub.eq(subj.as_str()),
+            title.eq(&prev.title),
+            auth.eq(serde_json::to_string(&prev.auth)?),
+            abs.eq(&prev.abs),
+            queued_at.eq(sql_time(&Utc::now())),
+        );
+        let inserted = transaction(conn, || {
+            Ok(dispatch!(conn, c => insert_into(pending_revisions).values(row).execute(c))?)
+        });
+        match inserted {
+            Ok(_) => Ok(()),
+            Err(ref e) if conflict(e) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Revisions held back by the instance, the oldest first, with the version
+    // they're held against in place of the current one
+    pub fn revisions(conn: SqlConn, inst: &str) -> Fallible<Vec<(MetaData, MetaData, Subject)>> {
+        use prv::dsl::*;
+
+        dispatch!(conn, c => pending_revisions
+            .select((arxiv_id, sub, title, auth, abs))
+            .filter(instance.eq(inst))
+            .order(queued_at.asc())
+            .load::<(String, String, String, String, String)>(c))?
+            .into_iter()
+            .map(|(ix, s, t, a, ab)| -> Fallible<(MetaData, MetaData, Subject)> {
+                let md = paper::by_id(conn, Index::from_str(&ix)?)?;
+                let prev = MetaData {
+                    title: t,
+                    auth: serde_json::from_str(&a)?,
+                    abs: ab,
+                    ..md.clone()
+                };
+                Ok((prev, md, Subject::from_str(&s)?))
+            })
+            .try_fold(Vec::new(), try_fold_helper)
+    }
+
+    pub fn remove_revision(conn: SqlConn, idx: &Index, inst: &str) -> Fallible<()> {
+        use prv::dsl::*;
+
+        dispatch!(conn, c => delete(pending_revisions.find((idx.to_string(), inst))).execute(c))?;
+        Ok(())
+    }
+}
+
+// A row of `authors`
+#[derive(Queryable, Debug)]
+pub struct AuthorRow {
+    pub id: i32,
+    pub name: String,
+    pub norm_name: String,
+    pub family: String,
+    pub orcid: Option<String>,
+    pub arxiv_auth_id: Option<String>,
+}
+
+pub mod author {
+    use super::*;
+
+    // Find the identity of an author name or create a new one, returns the author id
+    pub fn upsert(conn: SqlConn, raw: &str) -> Fallible<i32> {
+        use at::dsl::*;
+
+        let aut = Author::lenient(raw);
+        let known = dispatch!(conn, c => authors
+            .select(id)
+            .filter(norm_name.eq(aut.key()))
+            .first::<i32>(c)
+            .optional())?;
+
+        let known = if known.is_none() {
+            use av::dsl::*;
+            dispatch!(conn, c => author_variants
+                .select(author_id)
+                .filter(variant.eq(raw))
+                .first::<i32>(c)
+                .optional())?
+        } else { known };
+
+        let aid = if let Some(aid) = known {
+            aid
+        } else {
+            let row = (
+                name.eq(raw),
+                norm_name.eq(aut.key()),
+                family.eq(aut.family()),
+            );
+            // Another instance may insert the same author in the meantime, take
+            // that one then
+            let inserted = transaction(conn, || {
+                Ok(dispatch!(conn, c => insert_into(authors).values(row).execute(c))?)
+            });
+            match inserted {
+                Ok(_) => (),
+                Err(ref e) if conflict(e) => (),
+                Err(e) => return Err(e),
+            }
+            dispatch!(conn, c => authors
+                .select(id)
+                .filter(norm_name.eq(aut.key()))
+                .first::<i32>(c))?
+        };
+
+        add_variant(conn, aid, raw)?;
+        Ok(aid)
+    }
+
+    // Make the keys of the authors listed in `author_rekey` again, merging those
+    // which turn out to be the same person. Only authors backfilled in SQL are
+    // listed, see the author_identity migration.
+    pub fn rekey(conn: SqlConn) -> Fallible<()> {
+        let ids = {
+            use ark::dsl::*;
+            dispatch!(conn, c => author_rekey.select(author_id).load::<i32>(c))?
+        };
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut merged = 0;
+        for aid in &ids {
+            transaction(conn, || {
+                use at::dsl::*;
+
+                let raw = dispatch!(conn, c => authors
+                    .find(aid)
+                    .select(name)
+                    .first::<String>(c)
+                    .optional())?;
+                if let Some(raw) = raw {
+                    let aut = Author::lenient(&raw);
+                    let same = dispatch!(conn, c => authors
+                        .select(id)
+                        .filter(norm_name.eq(aut.key()))
+                        .filter(id.ne(aid))
+                        .first::<i32>(c)
+                        .optional())?;
+                    if let Some(same) = same {
+                        merge(conn, *aid, same)?;
+                        merged += 1;
+                    } else {
+                        dispatch!(conn, c => update(authors.find(aid))
+                            .set((norm_name.eq(aut.key()), family.eq(aut.family())))
+                            .execute(c))?;
+                    }
+                }
+
+                use ark::dsl::*;
+                dispatch!(conn, c => delete(author_rekey.find(aid)).execute(c))?;
+                Ok(())
+            })?;
+        }
+        info!("已重新计算{}位作者的标识，合并了其中{}位", ids.len(), merged);
+        Ok(())
+    }
+
+    // Move the names and papers of an author to another one
+    fn merge(conn: SqlConn, from: i32, into: i32) -> Fallible<()> {
+        let names = {
+            use av::dsl::*;
+            let names = dispatch!(conn, c => author_variants
+                .select(variant)
+                .filter(author_id.eq(from))
+                .load::<String>(c))?;
+            dispatch!(conn, c => delete(author_variants.filter(author_id.eq(from))).execute(c))?;
+            names
+        };
+        for raw in names {
+            add_variant(conn, into, &raw)?;
+        }
+
+        {
+            use ap::dsl::*;
+            // A paper listing both keeps the other one
+            let both = dispatch!(conn, c => author_papers
+                .select(arxiv_id)
+                .filter(author_id.eq(into))
+                .load::<String>(c))?;
+            dispatch!(conn, c => delete(author_papers
+                .filter(author_id.eq(from))
+                .filter(arxiv_id.eq_any(&both)))
+                .execute(c))?;
+            dispatch!(conn, c => update(author_papers.filter(author_id.eq(from)))
+                .set(author_id.eq(into))
+                .execute(c))?;
+        }
+
+        use at::dsl::*;
+        dispatch!(conn, c => delete(authors.find(from)).execute(c))?;
+        Ok(())
+    }
+
+    pub fn add_variant(conn: SqlConn, aid: i32, raw: &str) -> Fallible<()> {
+        use av::dsl::*;
+
+        if dispatch!(conn, c => author_variants
+            .find((aid, raw))
+            .first::<(i32, String)>(c)
+            .optional())?
+            .is_none() {
+            let row = (author_id.eq(aid), variant.eq(raw));
+            let inserted = transaction(conn, || {
+                Ok(dispatch!(conn, c => insert_into(author_variants).values(row).execute(c))?)
+            });
+            match inserted {
+                Ok(_) => (),
+                Err(ref e) if conflict(e) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn by_ids(conn: SqlConn, ids: &[i32]) -> Fallible<Vec<AuthorRow>> {
+        use at::dsl::*;
+
+        Ok(dispatch!(conn, c => authors
+            .filter(id.eq_any(ids))
+            .order(id.asc())
+            .load::<AuthorRow>(c))?)
+    }
+
+    pub fn variants(conn: SqlConn, aid: i32) -> Fallible<Vec<String>> {
+        use av::dsl::*;
+
+        Ok(dispatch!(conn, c => author_variants
+            .select(variant)
+            .filter(author_id.eq(aid))
+            .order(variant.asc())
+            .load::<String>(c))?)
+    }
+
+    // The arXiv API gives neither id, so they're set by hand, see `arxiv_bot author`.
+    // Only the given ones are changed.
+    pub fn set_ids(conn: SqlConn, aid: i32, orcid_: Option<&str>, arxiv_id: Option<&str>) -> Fallible<()> {
+        use at::dsl::*;
+
+        transaction(conn, || {
+            if let Some(o) = orcid_ {
+                dispatch!(conn, c => update(authors.find(aid))
+                    .set(orcid.eq(o))
+                    .execute(c))?;
+            }
+            if let Some(a) = arxiv_id {
+                dispatch!(conn, c => update(authors.find(aid))
+                    .set(arxiv_auth_id.eq(a))
+                    .execute(c))?;
+            }
+            Ok(())
+        })
+    }
+
+    // All author ids which may be referred by the given name, an ORCID or an
+    // arXiv author id refers to its author only
+    pub fn lookup(conn: SqlConn, raw: &str) -> Fallible<Vec<i32>> {
+        use at::dsl::*;
+
+        let known = dispatch!(conn, c => authors
+            .select(id)
+            .filter(orcid.eq(raw).or(arxiv_auth_id.eq(raw)))
+            .load::<i32>(c))?;
+        if !known.is_empty() {
+            return Ok(known);
+        }
+
+        let aut = Author::lenient(raw);
+        let mut ids = dispatch!(conn, c => authors
+            .select((id, name))
+            .filter(family.eq(aut.family()))
+            .load::<(i32, String)>(c))?
+            .into_iter()
+            .filter(|(_, n)| Author::lenient(n).matches(&aut))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        {
+            use av::dsl::*;
+            for i in dispatch!(conn, c => author_variants
+                .select(author_id)
+                .filter(variant.eq(raw))
+                .load::<i32>(c))? {
+                if !ids.contains(&i) {
+                    ids.push(i);
+                }
+            }
+        }
+
+        Ok(ids)
     }
 }
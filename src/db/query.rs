@@ -0,0 +1,284 @@
+use super::*;
+use std::collections::HashMap;
+
+// SQLite limits the number of bound parameters to 999 by default
+const CHUNK: usize = 500;
+
+// A composable query over the paper store, e.g.
+//
+// Query::new()
+//     .subs(vec![Subject::math_LO])
+//     .since(tm)
+//     .published(false)
+//     .limit(20)
+//     .load(conn)?
+//
+// Papers are ordered by their arXiv ids, use `after` with the last index of
+// a page to fetch the next one, or `offset` for plain pagination.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    subs: Vec<Subject>,
+    prim_sub: Option<Subject>,
+    auths: Vec<String>,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    published: Option<bool>,
+    keyword: Option<String>,
+    after: Option<Index>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Papers listed in any of the subjects
+    pub fn subs(mut self, subs: Vec<Subject>) -> Self {
+        self.subs = subs;
+        self
+    }
+
+    pub fn prim_sub(mut self, sub: Subject) -> Self {
+        self.prim_sub = Some(sub);
+        self
+    }
+
+    // Papers written by any of the authors, matched fuzzily
+    pub fn auths(mut self, auths: Vec<String>) -> Self {
+        self.auths = auths;
+        self
+    }
+
+    // Announced at or after the time
+    pub fn since(mut self, tm: DateTime<FixedOffset>) -> Self {
+        self.since = Some(tm);
+        self
+    }
+
+    // Announced before the time
+    pub fn until(mut self, tm: DateTime<FixedOffset>) -> Self {
+        self.until = Some(tm);
+        self
+    }
+
+    // Whether a pin has been published for the paper
+    pub fn published(mut self, published: bool) -> Self {
+        self.published = Some(published);
+        self
+    }
+
+    // Substring of the title or the abstract, see `paper::search` for full-text queries
+    pub fn keyword(mut self, keyword: &str) -> Self {
+        self.keyword = Some(keyword.to_owned());
+        self
+    }
+
+    pub fn after(mut self, idx: Index) -> Self {
+        self.after = Some(idx);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn load(&self, conn: SqlConn) -> Fallible<MDList> {
+        let ids = if self.auths.is_empty() {
+            Vec::new()
+        } else {
+            self.auths
+                .iter()
+                .map(|a| author::lookup(conn, a))
+                .try_fold(Vec::new(), try_fold_helper)?
+                .concat()
+        };
+
+        // The author ids are bound in chunks, each of them is a query of its
+        // own then, which are merged and paged here
+        let chunks = if self.auths.is_empty() {
+            vec![&[][..]]
+        } else {
+            ids.chunks(CHUNK).collect::<Vec<_>>()
+        };
+        let paged = chunks.len() <= 1;
+        // Then every chunk needs the papers up to the end of the page
+        let (limit, offset) = if paged {
+            (self.limit, self.offset)
+        } else {
+            (self.limit.map(|l| l + self.offset.unwrap_or(0)), None)
+        };
+
+        let mut rows = Vec::new();
+        for chunk in chunks {
+            // The boxed query is tied to a backend, so it's built once per backend
+            rows.extend(dispatch!(conn, c => {
+                use pr::dsl::*;
+
+                let mut query = papers.into_boxed();
+
+                if !self.subs.is_empty() {
+                    use sb::dsl::{sub, subjects, arxiv_id as sid};
+                    query = query.filter(arxiv_id.eq_any(subjects
+                        .select(sid)
+                        .filter(sub.eq_any(self.subs
+                            .iter()
+                            .map(|s| s.as_str().to_owned())
+                            .collect::<Vec<_>>()))));
+                }
+
+                if let Some(ref sub) = self.prim_sub {
+                    query = query.filter(prim_sub.eq(sub.as_str().to_owned()));
+                }
+
+                if !self.auths.is_empty() {
+                    use ap::dsl::{author_id, author_papers, arxiv_id as aid};
+                    query = query.filter(arxiv_id.eq_any(author_papers
+                        .select(aid)
+                        .filter(author_id.eq_any(chunk.to_vec()))));
+                }
+
+                if let Some(ref tm) = self.since {
+                    query = query.filter(announced.ge(sql_time(tm)));
+                }
+
+                if let Some(ref tm) = self.until {
+                    query = query.filter(announced.lt(sql_time(tm)));
+                }
+
+                if let Some(published) = self.published {
+                    use p::dsl::{pins, arxiv_id as pid};
+                    query = if published {
+                        query.filter(arxiv_id.eq_any(pins.select(pid)))
+                    } else {
+                        query.filter(arxiv_id.ne_all(pins.select(pid)))
+                    };
+                }
+
+                if let Some(ref kw) = self.keyword {
+                    // The keyword is matched literally, `%` and `_` are wildcards of LIKE
+                    let pattern = format!("%{}%", kw.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+                    query = query.filter(title.like(pattern.clone()).escape('\\')
+                        .or(abstract_.like(pattern).escape('\\')));
+                }
+
+                if let Some(ref idx) = self.after {
+                    query = query.filter(arxiv_id.gt(idx.to_string()));
+                }
+
+                query = query.order(arxiv_id.asc());
+
+                if let Some(limit) = limit {
+                    query = query.limit(limit as i64);
+                }
+
+                if let Some(offset) = offset {
+                    // SQLite doesn't accept OFFSET without LIMIT
+                    if limit.is_none() {
+                        query = query.limit(i64::MAX);
+                    }
+                    query = query.offset(offset as i64);
+                }
+
+                query.load::<Paper>(c)
+            })?);
+        }
+
+        if !paged {
+            rows.sort_by(|a, b| a.arxiv_id.cmp(&b.arxiv_id));
+            rows.dedup_by(|a, b| a.arxiv_id == b.arxiv_id);
+            rows = rows
+                .into_iter()
+                .skip(self.offset.unwrap_or(0))
+                .take(self.limit.unwrap_or(usize::MAX))
+                .collect();
+        }
+        assemble(conn, rows)
+    }
+}
+
+// Load the papers with the given ids, keeping the order of the ids
+pub fn by_ids(conn: SqlConn, ids: &[String]) -> Fallible<MDList> {
+    use pr::dsl::*;
+
+    let mut rows = HashMap::new();
+    for chunk in ids.chunks(CHUNK) {
+        for p in dispatch!(conn, c => papers
+            .filter(arxiv_id.eq_any(chunk.to_vec()))
+            .load::<Paper>(c))? {
+            rows.insert(p.arxiv_id.clone(), p);
+        }
+    }
+
+    assemble(conn, ids
+        .iter()
+        .filter_map(|ix| rows.remove(ix))
+        .collect())
+}
+
+// Fetch subjects and authors of all papers with one query per table
+// instead of two per paper
+pub(super) fn assemble(conn: SqlConn, rows: Vec<Paper>) -> Fallible<MDList> {
+    let ids = rows
+        .iter()
+        .map(|p| p.arxiv_id.clone())
+        .collect::<Vec<_>>();
+
+    let mut subs: HashMap<String, Vec<String>> = HashMap::new();
+    let mut auths: HashMap<String, Vec<(String, Option<String>)>> = HashMap::new();
+
+    for chunk in ids.chunks(CHUNK) {
+        {
+            use sb::dsl::*;
+            for (ix, s) in dispatch!(conn, c => subjects
+                .select((arxiv_id, sub))
+                .filter(arxiv_id.eq_any(chunk.to_vec()))
+                .load::<(String, String)>(c))? {
+                subs.entry(ix).or_default().push(s);
+            }
+        }
+
+        {
+            use ap::dsl::*;
+            for (ix, a, af) in dispatch!(conn, c => author_papers
+                .select((arxiv_id, auth, affiliation))
+                .filter(arxiv_id.eq_any(chunk.to_vec()))
+                .order((arxiv_id.asc(), position.asc()))
+                .load::<(String, String, Option<String>)>(c))? {
+                auths.entry(ix).or_default().push((a, af));
+            }
+        }
+    }
+
+    rows.into_iter()
+        .map(|p| -> Fallible<MetaData> {
+            let (names, affils) = auths
+                .remove(&p.arxiv_id)
+                .unwrap_or_default()
+                .into_iter()
+                .unzip();
+
+            Ok(MetaData {
+                index: Index::from_str(&p.arxiv_id)?,
+                title: p.title,
+                abs: p.abstract_,
+                prim_sub: Subject::from_str(&p.prim_sub)?,
+                sub: subs
+                    .remove(&p.arxiv_id)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|s| Subject::from_str(s))
+                    .try_fold(Vec::new(), try_fold_helper)?,
+                auth: names,
+                affil: affils,
+            })
+        })
+        .try_fold(Vec::new(), try_fold_helper)
+}
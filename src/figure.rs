@@ -0,0 +1,200 @@
+use super::prelude::*;
+use flate2::read::GzDecoder;
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// [images]
+// thumbnail = true     # the first page of the PDF, needs `pdftoppm` from poppler
+// figure = true        # the first figure in the source tarball
+// max_count = 2        # extra images per post
+// max_size = 5242880   # in bytes, larger images are skipped
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Images {
+    #[serde(default)]
+    thumbnail: bool,
+    #[serde(default)]
+    figure: bool,
+    max_count: Option<usize>,
+    max_size: Option<u64>,
+}
+
+// Sources larger than this are not downloaded in full
+const MAX_SOURCE: u64 = 50 << 20;
+const IMAGE_EXTS: &[&str] = &["png", "jpg", "jpeg", "pdf"];
+
+impl Images {
+    pub fn check(&self) -> Fallible<()> {
+        if self.thumbnail || self.figure {
+            // Figures in PDF are converted as well
+            let found = Command::new("pdftoppm").arg("-v").output().is_ok();
+            ensure!(found, "找不到`pdftoppm`，请安装poppler");
+        }
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        (self.thumbnail || self.figure) && self.max_count() > 0
+    }
+
+    fn max_count(&self) -> usize {
+        self.max_count.unwrap_or(2)
+    }
+
+    fn max_size(&self) -> u64 {
+        self.max_size.unwrap_or(5 << 20)
+    }
+
+    // Extra images of the paper, the thumbnail first. They're optional, so
+    // failures are logged only.
+    pub fn collect(&self, md: &MetaData) -> Vec<Vec<u8>> {
+        // pdftoppm works on files, keep them apart from other instances and
+        // runs. Old style ids have a slash.
+        let name = md.index.to_string().replace('/', "_");
+        let scratch = match Scratch::new(std::env::temp_dir().join(format!("arxiv_bot-{}-{}", std::process::id(), name))) {
+            Ok(dir) => dir,
+            Err(e) => {
+                warn!("无法创建{}的临时目录：{}", md.index, e);
+                return Vec::new();
+            },
+        };
+        let dir = scratch.0.as_path();
+
+        let mut images = Vec::new();
+        let mut add = |what: &str, res: Fallible<Option<PathBuf>>| {
+            match res.and_then(|p| Ok(p.map(fs::read).transpose()?)) {
+                Ok(Some(bytes)) => if bytes.len() as u64 <= self.max_size() {
+                    images.push(bytes);
+                } else {
+                    info!("{}的{}过大（{}字节），已跳过", md.index, what, bytes.len());
+                },
+                Ok(None) => (),
+                Err(e) => warn!("获取{}的{}失败：{}", md.index, what, e),
+            }
+        };
+
+        if self.thumbnail {
+            add("首页缩略图", thumbnail(&md.index, dir));
+        }
+        if self.figure {
+            add("首张图片", figure(&md.index, dir));
+        }
+
+        images.truncate(self.max_count());
+        images
+    }
+}
+
+// A temporary directory, removed when dropped
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(dir: PathBuf) -> Fallible<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Scratch(dir))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            warn!("无法删除临时目录{}：{}", self.0.display(), e);
+        }
+    }
+}
+
+fn thumbnail(idx: &Index, dir: &Path) -> Fallible<Option<PathBuf>> {
+    let pdf = dir.join("paper.pdf");
+    let mut resp = reqwest::get(&format!("https://arxiv.org/pdf/{}", idx))?;
+    check_status_code(&mut resp)?;
+    std::io::copy(&mut resp.take(MAX_SOURCE), &mut fs::File::create(&pdf)?)?;
+
+    pdftoppm(&pdf, &dir.join("thumbnail")).map(Some)
+}
+
+// The first image included by the TeX files, or the first image in the tarball
+fn figure(idx: &Index, dir: &Path) -> Fallible<Option<PathBuf>> {
+    let mut resp = reqwest::get(&format!("https://arxiv.org/e-print/{}", idx))?;
+    check_status_code(&mut resp)?;
+    let mut src = Vec::new();
+    resp.take(MAX_SOURCE).read_to_end(&mut src)?;
+
+    // Papers without source come as a PDF, and a single TeX file is gzipped
+    // without tar, neither has figures
+    if !src.starts_with(&[0x1f, 0x8b]) {
+        return Ok(None);
+    }
+    let mut tarball = Vec::new();
+    GzDecoder::new(&src[..]).take(MAX_SOURCE).read_to_end(&mut tarball)?;
+    if tarball.len() < 262 || &tarball[257..262] != b"ustar" {
+        return Ok(None);
+    }
+
+    let mut tex = String::new();
+    let mut images = Vec::new();
+    for entry in tar::Archive::new(&tarball[..]).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let ext = ext(&path);
+        if ext == "tex" {
+            let mut text = Vec::new();
+            entry.read_to_end(&mut text)?;
+            tex.push_str(&String::from_utf8_lossy(&text));
+        } else if IMAGE_EXTS.contains(&ext.as_str()) {
+            images.push(path);
+        }
+    }
+
+    let include = Regex::new(r"\\includegraphics\s*(\[[^\]]*\])?\s*\{([^}]+)\}")?;
+    let chosen = include
+        .captures_iter(&tex)
+        .filter_map(|c| {
+            let name = c[2].trim().trim_start_matches("./").to_owned();
+            images.iter().find(|i| {
+                let stem = i.rsplitn(2, '.').last().unwrap_or_default();
+                i.ends_with(&name) || stem.ends_with(&name)
+            })
+        })
+        .next()
+        .or_else(|| images.first())
+        .cloned();
+    let chosen = match chosen {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    let out = dir.join(format!("figure.{}", ext(&chosen)));
+    for entry in tar::Archive::new(&tarball[..]).entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_string_lossy() == chosen {
+            std::io::copy(&mut entry, &mut fs::File::create(&out)?)?;
+            break;
+        }
+    }
+
+    if ext(&chosen) == "pdf" {
+        pdftoppm(&out, &dir.join("figure")).map(Some)
+    } else {
+        Ok(Some(out))
+    }
+}
+
+// Render the first page to `out`.png
+fn pdftoppm(pdf: &Path, out: &Path) -> Fallible<PathBuf> {
+    let status = Command::new("pdftoppm")
+        .args(["-png", "-f", "1", "-l", "1", "-singlefile", "-scale-to", "1200"])
+        .arg(pdf)
+        .arg(out)
+        .status()?;
+    ensure!(status.success(), "pdftoppm运行失败：{}", status);
+    Ok(out.with_extension("png"))
+}
+
+fn ext(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map_or_else(String::new, |e| e.to_string_lossy().to_lowercase())
+}
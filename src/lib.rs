@@ -1,5 +1,6 @@
-#![feature(try_from)]
 #![allow(non_camel_case_types)]
+// The derives of diesel 1.4 put their impls in a const block
+#![allow(non_local_definitions)]
 
 #[macro_use]
 extern crate diesel; // for the schema.rs auto generated by diesel
@@ -15,7 +16,6 @@ pub mod prelude {
     };
 
     pub use log::*;
-    pub use toml::Value as Env;
     pub use failure::{
         bail,
         ensure,
@@ -25,12 +25,16 @@ pub mod prelude {
     };
 
     pub use serde::Deserialize;
+    pub use schemars::JsonSchema;
     pub use regex::Regex;
     pub use chrono::{
         DateTime,
+        Utc,
+        TimeZone,
         FixedOffset,
         Duration,
         NaiveTime,
+        NaiveDateTime,
     };
     pub use reqwest::{
         Url,
@@ -42,10 +46,20 @@ pub mod prelude {
         db::{
             pin,
             paper,
+            author,
+            pending,
+            stats,
+            article,
+            translation,
+            score,
+            digest,
             SqlConn,
+            PinRow,
+            AuthorRow,
         },
         arxiv_api::{
             Index,
+            Author,
             Subject,
             MetaData,
             fetch::{
@@ -57,9 +71,31 @@ pub mod prelude {
             List,
             ContentList,
             ClientExt,
-            pin::Pin,
+            Session,
+            SessionState,
+            pin::{Pin, PinState, PinStatus},
+            article::Article,
         },
-        publisher::Publisher,
+        publisher::{Publisher, Extra},
+        coord::Role,
+        signal::Signals,
+        revision::{Revision, Threshold},
+        db::Conn,
+        coord::Coord,
+        timer::Timer,
+        scraper::Scraper,
+        publisher::Zhihu,
+        log::Logger,
+        alert::Alert,
+        pins::Pins,
+        roundup::Roundup,
+        translate::Translation,
+        summary::Summary,
+        ranking::{Ranking, Score},
+        queue::Queue,
+        report::{By, Format},
+        config::Config,
+        secret::redact,
     };
 
     pub mod init {
@@ -68,6 +104,26 @@ pub mod prelude {
         pub use crate::timer::init as timer;
         pub use crate::scraper::init as scraper;
         pub use crate::publisher::init as pub_;
+        pub use crate::coord::init as coord;
+        pub use crate::signal::init as signal;
+        pub use crate::alert::init as alert;
+        pub use crate::pins::init as pins;
+        pub use crate::roundup::init as roundup;
+        pub use crate::translate::init as translate;
+        pub use crate::summary::init as summary;
+        pub use crate::ranking::init as ranking;
+        pub use crate::queue::init as queue;
+    }
+
+    pub mod report {
+        pub use crate::report::{rank, render, Row};
+    }
+
+    // Durations are written as TOML times, e.g. `interval = 08:00:00`
+    pub fn toml_duration(tm: &toml::value::Datetime) -> Fallible<std::time::Duration> {
+        Ok(NaiveTime::parse_from_str(tm.to_string().as_str(), "%H:%M:%S")?
+            .signed_duration_since(NaiveTime::MIN)
+            .to_std()?)
     }
 
     pub fn check_status_code(resp: &mut Response) -> Fallible<()> {
@@ -96,9 +152,23 @@ pub mod prelude {
 }
 
 mod log;
+mod config;
+mod secret;
+mod alert;
+mod pins;
+mod report;
+mod roundup;
+mod coord;
+mod signal;
 mod timer;
 mod scraper;
+mod revision;
 mod publisher;
+mod figure;
+mod translate;
+mod summary;
+mod ranking;
+mod queue;
 
 mod db;
 mod schema;
@@ -1,30 +1,49 @@
 use super::prelude::*;
 use simplelog::*;
+use std::sync::RwLock;
+use lazy_static::lazy_static;
 
-#[derive(Debug, Deserialize)]
-struct Console { level: String }
-#[derive(Debug, Deserialize)]
-struct File { path: String, level: String }
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Console { level: String }
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct File { path: String, level: String }
 
 impl File {
-    fn parse(self) -> Fallible<(fs::File, LevelFilter)> {
+    fn parse(&self) -> Fallible<(fs::File, LevelFilter)> {
         let level = self.level.parse()?;
         let file = OpenOptions::new()
             .append(true)
             .create(true)
-            .open(self.path)?;
+            .open(&self.path)?;
 
         Ok((file, level))
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct Log {
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
     file: Option<Vec<File>>,
     console: Option<Console>,
 }
 
-impl Default for Log {
+impl LogConfig {
+    // Check the levels without opening the files
+    pub fn check(&self) -> Fallible<()> {
+        for level in self.console
+            .iter()
+            .map(|c| &c.level)
+            .chain(self.file.iter().flatten().map(|f| &f.level)) {
+            level.parse::<LevelFilter>()
+                .map_err(|_| err_msg(format!("无效的日志级别：{}", level)))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for LogConfig {
     fn default() -> Self {
         let f = vec![ File {
             path: "arxiv_bot.log".to_owned(),
@@ -38,46 +57,75 @@ impl Default for Log {
     }
 }
 
-const LOG_KEY: &str = "log";
+lazy_static! {
+    static ref CURRENT: RwLock<Option<Box<dyn SharedLogger>>> = RwLock::new(None);
+}
+
+// The `log` crate only accepts one logger for the whole process, so a proxy is
+// installed and the loggers behind it are swapped on reload
+struct Proxy;
 
-pub fn init(env: &Env) -> Fallible<()> {
-    let conf = env
-        .get(LOG_KEY)
-        .map_or_else(|| Ok(Log::default()), |v| toml::from_str(v.to_string().as_str()))?;
+static PROXY: Proxy = Proxy;
 
+impl Log for Proxy {
+    fn enabled(&self, meta: &Metadata) -> bool {
+        CURRENT.read().unwrap().as_ref().is_some_and(|l| l.enabled(meta))
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(l) = CURRENT.read().unwrap().as_ref() {
+            if !l.enabled(record.metadata()) {
+                return;
+            }
+            // Secrets may show up in error messages and responses
+            let msg = redact(&record.args().to_string());
+            l.log(&Record::builder()
+                .args(format_args!("{}", msg))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build());
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(l) = CURRENT.read().unwrap().as_ref() {
+            l.flush();
+        }
+    }
+}
+
+// Loggers built from the config but not in use yet
+pub struct Logger(Box<dyn SharedLogger>);
+
+impl Logger {
+    pub fn apply(self) {
+        if CURRENT.read().unwrap().is_none() {
+            // Only fails if another logger has been set, which never happens
+            let _ = set_logger(&PROXY);
+        }
+        set_max_level(self.0.level());
+        *CURRENT.write().unwrap() = Some(self.0);
+    }
+}
+
+pub fn init(conf: &LogConfig) -> Fallible<Logger> {
+    let default = LogConfig::default();
     let conf = if conf.file.is_none() && conf.console.is_none() {
-        eprintln!("检测到`{}`项存在但配置不正确，载入默认日志配置", LOG_KEY);
-        Log::default()
+        eprintln!("检测到`log`项存在但配置不正确，载入默认日志配置");
+        &default
     } else { conf };
 
-    match conf {
-        Log { file: Some(mut f), console: None } => {
-            if f.len() == 1 {
-                let (f, l) = f.pop().unwrap().parse()?;
-                WriteLogger::init(l, Config::default(), f)?;
-            } else {
-                let mut logger: Vec<Box<dyn SharedLogger>> = Vec::new();
-                for f in f {
-                    let (f, l) = f.parse()?;
-                    logger.push(WriteLogger::new(l, Config::default(), f));
-                }
-                CombinedLogger::init(logger)?;
-            }
-        },
-        Log { file: None, console: Some(c) } => {
-            TermLogger::init(c.level.parse()?, Config::default())?;
-        },
-        Log { file: Some(f), console: Some(c) } => {
-            let mut logger: Vec<Box<dyn SharedLogger>> = Vec::new();
-            logger.push(TermLogger::new(c.level.parse()?, Config::default()).unwrap());
-            for f in f {
-                let (f, l) = f.parse()?;
-                logger.push(WriteLogger::new(l, Config::default(), f));
-            }
-            CombinedLogger::init(logger)?;
-        },
-        _ => unreachable!()
-    };
+    let mut logger: Vec<Box<dyn SharedLogger>> = Vec::new();
+    if let Some(ref c) = conf.console {
+        logger.push(TermLogger::new(c.level.parse()?, simplelog::Config::default())
+            .ok_or_else(|| err_msg("无法输出到终端"))?);
+    }
+    for f in conf.file.iter().flatten() {
+        let (f, l) = f.parse()?;
+        logger.push(WriteLogger::new(l, simplelog::Config::default(), f));
+    }
 
-    Ok(())
+    Ok(Logger(CombinedLogger::new(logger)))
 }
@@ -1,62 +1,572 @@
-#![feature(try_from)]
-
 use arxiv_bot::prelude::*;
+use chrono::NaiveDate;
 use std::{fs, env, process};
 
+const SESSION_RETRIES: u32 = 3;
+const SESSION_RETRY_SECS: u64 = 60;
+// How often followers in the queue look for the owner's pin
+const QUEUE_POLL_SECS: u64 = 30;
+
 fn main() -> Fallible<()> {
-    let path = env::args()
-        .nth(1)
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let cmd = match args.first().map(String::as_str) {
+        Some("search") | Some("config") | Some("pin") | Some("report") | Some("roundup")
+            | Some("queue") | Some("author") => Some(args.remove(0)),
+        _ => None,
+    };
+
+    // `arxiv_bot /path/to/conf` is kept for compatibility
+    let path = opt(&mut args, "-c")
+        .or_else(|| if cmd.is_none() { args.pop() } else { None })
         .unwrap_or_else(|| "arxiv_bot.toml".to_owned());
 
-    fs::read_to_string(path.as_str())
-        .map_err(Error::from)
-        .and_then(|config| {
-            config.parse::<Env>()
-                .map_err(Error::from)
-        })
-        .map_err(|e| {
-            eprintln!("{}: {}", path, e);
-            process::exit(1)
-        })
-        .and_then(launch)
-        .map_err(handle)
-}
-
-fn launch(env: Env) -> Fallible<()> {
-    init::log(&env)?;
-
-    let conn = init::db(&env)?;
-    let scraper = init::scraper(&env)?;
-    let publisher = init::pub_(&env)?;
-
-    let timer = init::timer(&env, move |t| {
-        loop {
-            let worker = || -> Fallible<()> {
-                for rss in scraper.scrape(&conn)? {
-                    for ix in rss.index() {
-                        let md = MetaData::try_from(ix)?;
-                        paper::insert(&conn, rss.sub.clone(), md.clone(), rss.last())?;
-                        let pin = publisher.publish(&md, None)?;
-                        pin::insert(&conn, pin, None, md.index.clone())?;
+    if cmd.as_deref() == Some("config") {
+        return config(&path, args);
+    }
+
+    let conf = load(&path).unwrap_or_else(|e| {
+        eprintln!("{}:\n{}", path, redact(&e.to_string()));
+        process::exit(1)
+    });
+
+    match cmd.as_deref() {
+        Some("search") => search(conf, args),
+        Some("pin") => manage(conf, args),
+        Some("report") => report(conf, args),
+        Some("roundup") => roundup(conf, args),
+        Some("queue") => queue(conf, args),
+        Some("author") => author(conf, args),
+        _ => launch(&path, conf).map_err(handle),
+    }
+}
+
+fn load(path: &str) -> Fallible<Config> {
+    fs::read_to_string(path)?.parse::<Config>()
+}
+
+// arxiv_bot config check [-c conf]
+// arxiv_bot config schema > arxiv_bot.schema.json
+fn config(path: &str, args: Vec<String>) -> Fallible<()> {
+    match args.first().map(String::as_str) {
+        Some("check") => match Config::parse(&fs::read_to_string(path)?) {
+            Ok(_) => {
+                println!("{}：配置正确", path);
+                Ok(())
+            },
+            Err(problems) => {
+                for p in problems {
+                    println!("{}：{}", path, redact(&p.to_string()));
+                }
+                process::exit(1)
+            },
+        },
+        Some("schema") => {
+            println!("{}", Config::schema()?);
+            Ok(())
+        },
+        _ => bail!("用法：arxiv_bot config check|schema"),
+    }
+}
+
+// Everything built from the config, swapped as a whole on reload
+struct Bot {
+    conn: Conn,
+    coord: Coord,
+    scraper: Scraper,
+    publisher: Zhihu,
+    threshold: Threshold,
+    alert: Alert,
+    pins: Pins,
+    roundup: Roundup,
+    translation: Translation,
+    summary: Summary,
+    ranking: Ranking,
+    queue: Queue,
+    // Publishing is paused unless the session is valid
+    state: SessionState,
+    // The expiry of the cookie alerted last, a refreshed cookie has another one
+    alerted: Option<DateTime<Utc>>,
+}
+
+// Build every component before using any of them, so that an invalid config
+// changes nothing
+fn build(conf: &Config) -> Fallible<(Logger, Timer, Bot)> {
+    Ok((init::log(&conf.log)?, init::timer(&conf.timer)?, Bot {
+        conn: init::db(&conf.db)?,
+        coord: init::coord(conf.coord.as_ref())?,
+        scraper: init::scraper(&conf.scraper)?,
+        publisher: init::pub_(&conf.auth, &conf.tags, &conf.images)?,
+        threshold: conf.revision.clone(),
+        alert: init::alert(&conf.alert)?,
+        pins: init::pins(&conf.sync)?,
+        roundup: init::roundup(&conf.roundup)?,
+        translation: init::translate(&conf.translate)?,
+        summary: init::summary(&conf.summary)?,
+        ranking: init::ranking(&conf.ranking)?,
+        queue: init::queue(&conf.queue)?,
+        state: SessionState::Valid,
+        alerted: None,
+    }))
+}
+
+fn launch(path: &str, mut conf: Config) -> Fallible<()> {
+    let (logger, mut timer, mut bot) = build(&conf)?;
+    logger.apply();
+
+    let sig = init::signal()?;
+    // Changing `watch` itself takes effect after a restart
+    if conf.reload.watch {
+        sig.watch(path);
+    }
+
+    let mut last = None;
+    loop {
+        let (b, l) = timer
+            .start(sig.clone(), last, bot, work(sig.clone()))
+            .join()
+            .map_err(|_| err_msg("定时器线程异常退出"))?;
+        bot = b;
+        last = l;
+
+        if sig.terminated() {
+            info!("收到退出信号，已停止运行");
+            break;
+        }
+        if !sig.take_reload() {
+            break;
+        }
+
+        match load(path).and_then(|new| Ok((build(&new)?, new))) {
+            Ok(((logger, tm, b), new)) => {
+                logger.apply();
+                let changes = conf.diff(&new);
+                if changes.is_empty() {
+                    info!("已重新载入配置文件{}，没有变化", path);
+                } else {
+                    info!("已重新载入配置文件{}，变化如下：", path);
+                    for c in changes {
+                        info!("    {}", c);
                     }
                 }
-                Ok(())
+                timer = tm;
+                bot = b;
+                conf = new;
+            },
+            Err(e) => error!("重新载入配置文件{}失败，继续使用原配置：{}", path, e),
+        }
+    }
+
+    log::logger().flush();
+    Ok(())
+}
+
+// One run of the bot, stopping after the current paper once terminated
+fn work(sig: Signals) -> impl FnMut(&mut Bot) + Send + 'static {
+    move |bot: &mut Bot| {
+        let Bot {
+            ref conn, ref coord, ref scraper, ref publisher, ref threshold, ref alert, ref pins, ref roundup,
+            ref translation, ref summary, ref ranking, ref queue, ref mut state,
+            ref mut alerted,
+        } = *bot;
+
+        let mut worker = || -> Fallible<()> {
+            coord.register(conn, &scraper.subjects()?)?;
+            translation.reset();
+
+            // Alert once per change instead of every run
+            let now = session(publisher, &sig)?;
+            if now != *state {
+                match now {
+                    SessionState::Valid => info!("知乎登录状态已恢复，继续发布"),
+                    ref s => alert.send(&format!("知乎登录状态异常：{}，暂停发布，新的论文将在恢复后发布", s)),
+                }
+            }
+            let held = now != SessionState::Valid;
+            *state = now;
+            let expires = publisher.expires();
+            if expires != *alerted && alert.expiry(expires) {
+                *alerted = expires;
+            }
+
+            let post = |md: &MetaData, sub: &Subject, refs: Option<Pin>| -> Fallible<()> {
+                // A repost has the headline only
+                let mut extra = Extra::default();
+                if refs.is_none() {
+                    summary.apply(conn, md, &mut extra)?;
+                    translation.apply(conn, md, &mut extra)?;
+                }
+                let pin = publisher.publish(md, &extra, refs.clone())?;
+                pin::insert(conn, pin, refs, md.index.clone(), coord.name(), sub)?;
+                pending::remove(conn, &md.index, coord.name(), sub)
             };
 
-            if let Err(e) = worker() {
-                error!("检测到错误，本次运行失败，下一次运行将在{}秒后.", t.as_secs());
-                error!("错误详情：{}", e);
+            // New papers are queued, reposts as they come, then our own ones
+            // ranked over all feeds, then those of other owners
+            let (mut owned, mut followed) = (Vec::new(), Vec::new());
+            for rss in scraper.scrape(conn)? {
+                for ix in rss.index() {
+                    // Stop fetching once terminated, the rest are picked up
+                    // by the next run
+                    if sig.terminated() {
+                        break;
+                    }
+
+                    let prev = if paper::exists(conn, &ix)? {
+                        Some(paper::by_id(conn, ix.clone())?)
+                    } else {
+                        None
+                    };
+                    let md = MetaData::try_from(ix)?;
+
+                    // A replacement of a published paper, only the owner announces it
+                    if let (Some(prev), Some(_)) = (prev, pin::original(conn, &md.index)?) {
+                        // Queued like the papers, it's announced when the queue drains
+                        if !Revision::diff(&prev, &md).is_empty() && coord.claim(conn, &md)? {
+                            pending::hold_revision(conn, &prev, coord.name(), &rss.sub)?;
+                            // Minor ones are stored too, so that they aren't found again
+                            paper::update(conn, md.clone())?;
+                        }
+                    }
+
+                    paper::insert(conn, rss.sub.clone(), md.clone(), rss.last())?;
+                    match coord.role(conn, &md, &rss.sub)? {
+                        Role::Owner => {
+                            ranking.record(conn, &md, &rss.sub)?;
+                            owned.push((md, rss.sub.clone()));
+                        },
+                        Role::Repost(_) => pending::push(conn, &md.index, coord.name(), &rss.sub)?,
+                        // Queued after ours, it waits there for the owner
+                        Role::Follower => followed.push((md.index, rss.sub.clone())),
+                        Role::Done => (),
+                    }
+                }
+            }
+
+            // Ranked even while paused, so that the queue holds the top ones
+            // only on resume. A paper of several feeds is ranked once.
+            let mut mds: Vec<MetaData> = Vec::new();
+            for (md, _) in &owned {
+                if !mds.iter().any(|m| m.index.to_string() == md.index.to_string()) {
+                    mds.push(md.clone());
+                }
+            }
+            let (top, _) = ranking.split(mds);
+            let top = top.iter().map(|md| md.index.to_string()).collect::<Vec<_>>();
+            for (md, sub) in &owned {
+                if top.contains(&md.index.to_string()) {
+                    pending::push(conn, &md.index, coord.name(), sub)?;
+                } else if !score::is_folded(conn, &md.index)? {
+                    // A cross-listed paper goes into the digest of one subject
+                    score::fold(conn, &md.index, sub, coord.name())?;
+                }
+            }
+            for (ix, sub) in &followed {
+                pending::push(conn, ix, coord.name(), sub)?;
+            }
+            if sig.terminated() {
+                return Ok(());
             }
 
-            sleep(t);
+            if held {
+                info!("发布已暂停，待发布队列中有{}条想法", pending::list(conn, coord.name())?.len());
+                return Ok(());
+            }
+            pins.sync(conn, publisher, coord.name())?;
+            roundup.run(conn, publisher, coord.name())?;
+
+            // Drain the queue last, for `max_drain` at most: the revisions, the
+            // digests, then the papers. Interrupted or out of time, the rest is
+            // posted by the next run.
+            let deadline = queue.deadline();
+            let wait = || queue.wait(conn, coord.name(), &sig, deadline);
+            for (prev, md, sub) in pending::revisions(conn, coord.name())? {
+                // Minor ones aren't announced
+                let rev = Revision::diff(&prev, &md);
+                if let (true, Some(orig)) = (threshold.significant(&rev), pin::original(conn, &md.index)?) {
+                    if !wait()? {
+                        return Ok(());
+                    }
+                    info!("{}有新版本：{:?}", md.index, rev);
+                    let pin = publisher.revise(&md, &rev, orig.clone())?;
+                    pin::insert(conn, pin, Some(orig), md.index.clone(), coord.name(), &sub)?;
+                }
+                pending::remove_revision(conn, &md.index, coord.name())?;
+            }
+            if !ranking.digest(conn, publisher, coord.name(), wait)? {
+                return Ok(());
+            }
+
+            // It's listed again after every post, since it may be reordered meanwhile
+            loop {
+                let (mut posted, mut waiting) = (false, false);
+                for (ix, sub, since) in pending::list(conn, coord.name())? {
+                    if sig.terminated() {
+                        return Ok(());
+                    }
+
+                    let md = paper::by_id(conn, ix.clone())?;
+                    let refs = match coord.role(conn, &md, &sub)? {
+                        Role::Owner => None,
+                        Role::Repost(orig) => Some(orig),
+                        Role::Follower if coord.take_over(conn, &md, since)? => None,
+                        // Skip it until the owner posts it
+                        Role::Follower => {
+                            waiting = true;
+                            continue;
+                        },
+                        Role::Done => {
+                            pending::remove(conn, &ix, coord.name(), &sub)?;
+                            continue;
+                        },
+                    };
+
+                    if !wait()? {
+                        return Ok(());
+                    }
+                    post(&md, &sub, refs)?;
+                    posted = true;
+                    break;
+                }
+
+                if posted {
+                    continue;
+                }
+                // Only followers are left, look for their owners' pins again
+                if !waiting || Utc::now() >= deadline
+                    || !sig.sleep(std::time::Duration::from_secs(QUEUE_POLL_SECS)) {
+                    return Ok(());
+                }
+            }
+        };
+
+        if let Err(e) = worker() {
+            error!("检测到错误，本次运行失败");
+            error!("错误详情：{}", e);
+        }
+    }
+}
+
+// An unknown state is often transient, e.g. an A/B test redirect, so check
+// again with growing delays before pausing for this run
+fn session(publisher: &Zhihu, sig: &Signals) -> Fallible<SessionState> {
+    let mut delay = std::time::Duration::from_secs(SESSION_RETRY_SECS);
+    for _ in 0..SESSION_RETRIES {
+        match publisher.state()? {
+            SessionState::Unknown(url) => {
+                warn!("无法判断知乎登录状态（跳转至{}），{}秒后重试", url, delay.as_secs());
+                if !sig.sleep(delay) {
+                    break;
+                }
+                delay *= 2;
+            },
+            state => return Ok(state),
         }
-    })?;
+    }
+    publisher.state()
+}
+
+// arxiv_bot search [-c conf] [--sub math.LO]... [--since 2019-01-01] [--until 2019-02-01] [--limit 20] query
+fn search(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    let conn = init::db(&conf.db)?;
+
+    let mut filter = paper::Filter::default();
+    while let Some(s) = opt(&mut args, "--sub") {
+        filter.subs.push(Subject::from_str(&s)?);
+    }
+    filter.since = opt(&mut args, "--since").map(|d| date(&d)).transpose()?;
+    // The end date is inclusive
+    filter.until = opt(&mut args, "--until").map(|d| date(&d)).transpose()?
+        .map(|d| d + Duration::days(1));
+    let limit = opt(&mut args, "--limit").map_or(Ok(20), |l| l.parse())?;
+
+    ensure!(!args.is_empty(), "缺少搜索内容");
+    for md in paper::search(&conn, &args.join(" "), &filter, limit)? {
+        let url: Url = (&md.index).into();
+        println!("{}\t{}\n\t{}\n\t{}", md.index, md.title, md.auth.join(", "), url);
+    }
+    Ok(())
+}
+
+// arxiv_bot pin show|delete|edit [-c conf] <arxiv_id> [text]
+fn manage(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    ensure!(args.len() >= 2, "用法：arxiv_bot pin show|delete|edit <arxiv_id> [text]");
+    let action = args.remove(0);
+    let idx = Index::from_str(&args.remove(0))?;
+    let text = if args.is_empty() { None } else { Some(args.join(" ")) };
+
+    let conn = init::db(&conf.db)?;
+    let publisher = init::pub_(&conf.auth, &conf.tags, &conf.images)?;
+    let pins = init::pins(&conf.sync)?;
+    match action.as_str() {
+        "show" => pins.show(&conn, &publisher, &idx),
+        "delete" => pins.delete(&conn, &publisher, &idx, init::coord(conf.coord.as_ref())?.name()),
+        "edit" => {
+            let md = paper::by_id(&conn, idx)?;
+            let mut extra = Extra::default();
+            init::summary(&conf.summary)?.apply(&conn, &md, &mut extra)?;
+            init::translate(&conf.translate)?.apply(&conn, &md, &mut extra)?;
+            pins.edit(&conn, &publisher, &md, &extra, text.as_deref())
+        },
+        _ => bail!("无效的操作：{}", action),
+    }
+}
+
+// arxiv_bot report [-c conf] [--by paper|subject|author] [--since 2019-01-01] [--until 2019-02-01]
+//                  [--format csv|json] [--limit 20]
+fn report(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    let conn = init::db(&conf.db)?;
+
+    let by = opt(&mut args, "--by").map_or(Ok(By::Paper), |b| b.parse())?;
+    let fmt = opt(&mut args, "--format").map_or(Ok(Format::Csv), |f| f.parse())?;
+    let since = opt(&mut args, "--since").map(|d| date(&d)).transpose()?;
+    // The end date is inclusive
+    let until = opt(&mut args, "--until").map(|d| date(&d)).transpose()?
+        .map(|d| d + Duration::days(1));
+    let limit = opt(&mut args, "--limit").map_or(Ok(20), |l| l.parse())?;
+    ensure!(args.is_empty(), "无效的参数：{}", args.join(" "));
+
+    let mut rows = report::rank(&conn, by, since, until)?;
+    rows.truncate(limit);
+    print!("{}", report::render(&rows, fmt)?);
+    Ok(())
+}
+
+// arxiv_bot roundup [-c conf] [--since 2019-01-01] [--until 2019-01-08] [--draft|--live]
+fn roundup(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    let conn = init::db(&conf.db)?;
+    let publisher = init::pub_(&conf.auth, &conf.tags, &conf.images)?;
+    let roundup = init::roundup(&conf.roundup)?;
+    let coord = init::coord(conf.coord.as_ref())?;
+
+    let draft = match (flag(&mut args, "--draft"), flag(&mut args, "--live")) {
+        (true, true) => bail!("`--draft`与`--live`只能指定其中一项"),
+        (false, true) => false,
+        (true, false) => true,
+        (false, false) => roundup.draft(),
+    };
+    // The end date is inclusive, the period defaults to the last `days` days
+    let until = opt(&mut args, "--until").map(|d| date(&d)).transpose()?
+        .map_or_else(Utc::now, |d| (d + Duration::days(1)).with_timezone(&Utc));
+    let since = opt(&mut args, "--since").map(|d| date(&d)).transpose()?
+        .map_or_else(|| until - Duration::days(roundup.days()), |d| d.with_timezone(&Utc));
+    ensure!(args.is_empty(), "无效的参数：{}", args.join(" "));
+
+    match roundup.publish(&conn, &publisher, coord.name(), since, until, draft)? {
+        Some(art) => println!("{}", art.url()),
+        None => println!("该时间段内没有发布的论文"),
+    }
+    Ok(())
+}
+
+// arxiv_bot queue list|clear [-c conf]
+// arxiv_bot queue move|remove [-c conf] <arxiv_id> [position]
+fn queue(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    const USAGE: &str = "用法：arxiv_bot queue list|move|remove|clear [arxiv_id] [position]";
+    ensure!(!args.is_empty(), USAGE);
+    let action = args.remove(0);
+
+    let conn = init::db(&conf.db)?;
+    let coord = init::coord(conf.coord.as_ref())?;
+    match (action.as_str(), args.len()) {
+        ("list", 0) => {
+            for (pos, (ix, sub, since)) in pending::list(&conn, coord.name())?.into_iter().enumerate() {
+                let since = since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                let title = paper::by_id(&conn, ix.clone()).map(|md| md.title).unwrap_or_default();
+                println!("{}\t{}\t{}\t{}\t{}", pos + 1, ix, sub, since, title);
+            }
+        },
+        // Positions are shown and given from 1
+        ("move", 2) => {
+            let to = args[1].parse::<usize>().map_err(|_| err_msg(USAGE))?;
+            ensure!(to > 0, "位置从1开始");
+            pending::move_to(&conn, &Index::from_str(&args[0])?, coord.name(), to - 1)?;
+        },
+        ("remove", 1) => {
+            let n = pending::remove_paper(&conn, &Index::from_str(&args[0])?, coord.name())?;
+            ensure!(n > 0, "{}不在队列中", args[0]);
+            println!("已从队列中移除{}条想法", n);
+        },
+        ("clear", 0) => println!("已清空队列，共移除{}条想法", pending::clear(&conn, coord.name())?),
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
+
+// arxiv_bot author show [-c conf] <name>
+// arxiv_bot author set [-c conf] <name|id> [--orcid 0000-0002-1825-0097] [--arxiv-id smith_j_1]
+fn author(conf: Config, mut args: Vec<String>) -> Fallible<()> {
+    const USAGE: &str = "用法：arxiv_bot author show|set <作者> [--orcid ORCID] [--arxiv-id arXiv作者ID]";
+    ensure!(!args.is_empty(), USAGE);
+    let action = args.remove(0);
+    let orcid = opt(&mut args, "--orcid");
+    let arxiv_id = opt(&mut args, "--arxiv-id");
+    ensure!(!args.is_empty(), USAGE);
+    let name = args.join(" ");
+
+    let conn = init::db(&conf.db)?;
+    // The ids listed by `show` pick one of several namesakes
+    let ids = match name.parse::<i32>() {
+        Ok(aid) => vec![aid],
+        Err(_) => author::lookup(&conn, &name)?,
+    };
+    let rows = author::by_ids(&conn, &ids)?;
+    ensure!(!rows.is_empty(), "找不到作者：{}", name);
+
+    match action.as_str() {
+        "show" => for row in rows {
+            println!(
+                "{}\t{}\tORCID: {}\tarXiv: {}\n\t{}",
+                row.id,
+                row.name,
+                row.orcid.as_deref().unwrap_or("-"),
+                row.arxiv_auth_id.as_deref().unwrap_or("-"),
+                author::variants(&conn, row.id)?.join(", "),
+            );
+        },
+        "set" => {
+            ensure!(orcid.is_some() || arxiv_id.is_some(), USAGE);
+            ensure!(rows.len() == 1, "{}可能指{}位作者，请使用`author show`列出的编号", name, rows.len());
+            if let Some(ref o) = orcid {
+                let valid = Regex::new(r"^\d{4}-\d{4}-\d{4}-\d{3}[\dX]$")?;
+                ensure!(valid.is_match(o), "无效的ORCID：{}", o);
+            }
+            author::set_ids(&conn, rows[0].id, orcid.as_deref(), arxiv_id.as_deref())?;
+        },
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
+
+// Take the value of an option like `--sub math.LO` out of the arguments
+fn opt(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let ix = args.iter().position(|a| a == name)?;
+    args.remove(ix);
+    if ix < args.len() {
+        Some(args.remove(ix))
+    } else {
+        None
+    }
+}
+
+// Take a flag like `--draft` out of the arguments
+fn flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(ix) => {
+            args.remove(ix);
+            true
+        },
+        None => false,
+    }
+}
 
-    timer.join().unwrap() // unreachable, safely unwrap
+fn date(d: &str) -> Fallible<DateTime<FixedOffset>> {
+    let d = NaiveDate::parse_from_str(d, "%Y-%m-%d")?;
+    Ok(d.and_time(NaiveTime::MIN).and_utc().fixed_offset())
 }
 
 fn handle(e: Error) -> Error {
     // TODO: HANDLE ERRORS
     error!("{}", e);
-    e
+    // The error is printed again on exit, outside the log
+    err_msg(redact(&e.to_string()))
 }
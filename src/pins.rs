@@ -0,0 +1,156 @@
+use super::prelude::*;
+
+// [sync]
+// enabled = true
+// days = 30            # only pins posted within this many days
+// interval = 12:00:00  # sync a pin at most once in this long
+// limit = 50           # pins per run
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+    #[serde(default = "default_true")]
+    enabled: bool,
+    days: Option<i64>,
+    #[schemars(with = "Option<String>")]
+    interval: Option<toml::value::Datetime>,
+    limit: Option<i64>,
+}
+
+fn default_true() -> bool { true }
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            days: None,
+            interval: None,
+            limit: None,
+        }
+    }
+}
+
+// Manages the published pins, and keeps the `pins` table in line with
+// what is on Zhihu
+#[derive(Debug)]
+pub struct Pins {
+    enabled: bool,
+    days: i64,
+    interval: Duration,
+    limit: i64,
+}
+
+impl Pins {
+    // Fetch the state of the pins due and record their engagement, returns
+    // them with their states
+    pub fn sync<P>(&self, conn: SqlConn, publisher: &P, inst: &str) -> Fallible<Vec<(Pin, PinState)>> where
+        P: Publisher<Handle = Pin, Error = Error>,
+    {
+        if !self.enabled {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        let mut synced = Vec::new();
+        for pin in pin::stale(conn, inst, now - Duration::days(self.days), now - self.interval, self.limit)? {
+            // One broken pin shouldn't stop the others
+            match publisher.fetch(&pin) {
+                Ok(state) => {
+                    if state.status != PinStatus::Visible {
+                        warn!("想法{}的状态为{}", pin.id, state.status);
+                    }
+                    pin::set_status(conn, &pin, state.status)?;
+                    stats::record(conn, &pin, &state)?;
+                    synced.push((pin, state));
+                },
+                Err(e) => warn!("同步想法{}失败：{}", pin.id, e),
+            }
+        }
+        // Digests are synced alike, but aren't returned since no paper is theirs
+        for pin in digest::stale(conn, inst, now - Duration::days(self.days), now - self.interval, self.limit)? {
+            match publisher.fetch(&pin) {
+                Ok(state) => {
+                    digest::set_status(conn, &pin, state.status)?;
+                    stats::record_digest(conn, &pin, &state)?;
+                },
+                Err(e) => warn!("同步合并的想法{}失败：{}", pin.id, e),
+            }
+        }
+        info!("已同步{}条想法", synced.len());
+        Ok(synced)
+    }
+
+    // arxiv_bot pin show <arxiv_id>
+    pub fn show<P>(&self, conn: SqlConn, publisher: &P, idx: &Index) -> Fallible<()> where
+        P: Publisher<Handle = Pin, Error = Error>,
+    {
+        let rows = pin::rows(conn, idx)?;
+        ensure!(!rows.is_empty(), "{}尚未发布", idx);
+
+        for row in rows {
+            let pin = Pin::from(row.id.as_str());
+            let kind = row.ref_id.map_or_else(|| "原始".to_owned(), |r| format!("转发{}", r));
+            match publisher.fetch(&pin) {
+                Ok(state) => {
+                    pin::set_status(conn, &pin, state.status)?;
+                    println!("{}\t{}\t{}\t{}\t赞{} 评论{} 转发{}\thttps://www.zhihu.com/pin/{}",
+                             pin.id, kind, row.instance.unwrap_or_default(), state.status,
+                             state.likes, state.comments, state.reposts, pin.id);
+                },
+                Err(e) => println!("{}\t{}\t{}\t{}（获取失败：{}）",
+                                   pin.id, kind, row.instance.unwrap_or_default(), row.status, e),
+            }
+        }
+        Ok(())
+    }
+
+    // arxiv_bot pin delete <arxiv_id>
+    // Deletes the pins of this instance only, reposts first
+    pub fn delete<P>(&self, conn: SqlConn, publisher: &P, idx: &Index, inst: &str) -> Fallible<()> where
+        P: Publisher<Handle = Pin, Error = Error>,
+    {
+        if let Some(pin) = score::digest_of(conn, idx)? {
+            bail!("{}在合并的想法{}中，无法单独删除", idx, pin.id);
+        }
+        let mut rows = pin::rows(conn, idx)?
+            .into_iter()
+            .filter(|r| r.instance.as_deref() == Some(inst))
+            .filter(|r| r.status != PinStatus::Deleted.as_str())
+            .collect::<Vec<_>>();
+        ensure!(!rows.is_empty(), "本实例没有{}的想法", idx);
+
+        rows.reverse();
+        for row in rows {
+            let pin = Pin::from(row.id.as_str());
+            publisher.delete(&pin)?;
+            pin::set_status(conn, &pin, PinStatus::Deleted)?;
+            println!("已删除想法{}", pin.id);
+        }
+        Ok(())
+    }
+
+    // arxiv_bot pin edit <arxiv_id> [text]
+    // Edits the original pin, rendering it again without the text
+    pub fn edit<P>(&self, conn: SqlConn, publisher: &P, md: &MetaData, extra: &Extra, text: Option<&str>) -> Fallible<()> where
+        P: Publisher<Handle = Pin, Error = Error>,
+    {
+        if let Some(pin) = score::digest_of(conn, &md.index)? {
+            bail!("{}在合并的想法{}中，无法单独修改", md.index, pin.id);
+        }
+        let pin = pin::by_arxiv_id(conn, md.index.clone())?;
+        publisher.edit(&pin, md, extra, text)?;
+        println!("已修改想法{}", pin.id);
+        Ok(())
+    }
+}
+
+pub fn init(conf: &SyncConfig) -> Fallible<Pins> {
+    Ok(Pins {
+        enabled: conf.enabled,
+        days: conf.days.unwrap_or(30),
+        interval: match conf.interval {
+            Some(ref itv) => Duration::from_std(toml_duration(itv)?)?,
+            None => Duration::hours(12),
+        },
+        limit: conf.limit.unwrap_or(50),
+    })
+}
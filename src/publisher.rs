@@ -1,6 +1,6 @@
 use super::prelude::*;
-
-const AUTH_KEY: &str = "auth";
+use crate::{secret, figure::Images};
+use std::collections::BTreeMap;
 
 pub trait Publisher
     where Self: Sized
@@ -8,46 +8,302 @@ pub trait Publisher
     type Auth;
     type Error;
     type Handle;
+    type Article;
 
     fn new(auth: Self::Auth) -> Fallible<Self>;
-    fn publish(&self, md: &MetaData, refs: Option<Self::Handle>) -> Result<Self::Handle, Self::Error>;
+    fn publish(&self, md: &MetaData, extra: &Extra, refs: Option<Self::Handle>) -> Result<Self::Handle, Self::Error>;
+    // Announce a new version of a published paper, referring to its original post
+    fn revise(&self, md: &MetaData, rev: &Revision, orig: Self::Handle) -> Result<Self::Handle, Self::Error>;
+    // Whether publishing is possible now, e.g. the login hasn't expired
+    fn state(&self) -> Result<SessionState, Self::Error>;
+    // When the credentials expire, if known
+    fn expires(&self) -> Option<DateTime<Utc>>;
+
+    fn fetch(&self, handle: &Self::Handle) -> Result<PinState, Self::Error>;
+    fn delete(&self, handle: &Self::Handle) -> Result<(), Self::Error>;
+    // Replace the content with the text, or render it again from the metadata
+    fn edit(&self, handle: &Self::Handle, md: &MetaData, extra: &Extra, text: Option<&str>) -> Result<(), Self::Error>;
+
+    // One post listing papers not posted alone, see `ranking`
+    fn digest(&self, sub: &Subject, mds: &[MetaData]) -> Result<Self::Handle, Self::Error>;
+
+    // A long-form post of the papers grouped by subject, left as a draft or
+    // published into the column
+    fn article(&self, title: &str, groups: &[(Subject, Vec<MetaData>)], column: &str, draft: bool)
+        -> Result<Self::Article, Self::Error>;
 }
 
-#[derive(Debug, Deserialize)]
+// What the stages between fetching the metadata and publishing add to a paper
+#[derive(Clone, Debug, Default)]
+pub struct Extra {
+    // Translated title and abstract, see `translate`
+    pub title: Option<String>,
+    pub abs: Option<String>,
+    // A sentence or two for long abstracts, see `summary`
+    pub tldr: Option<String>,
+}
+
+// The cookie is taken from the first one given of
+//   1. `cookie`, or the environment variable `ARXIV_BOT_AUTH_COOKIE`, see `config`
+//   2. `cookie_file`, which only the owner may read
+//   3. the systemd credential `zhihu_cookie`, i.e. `LoadCredential=zhihu_cookie:/path/to/cookie`
+#[derive(Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Auth {
-    cookie: String,
+    cookie: Option<String>,
+    cookie_file: Option<String>,
+    // Cookies refreshed by Zhihu are kept in this file, see `zhihu_api::Session`
+    jar: Option<String>,
+}
+
+const COOKIE_CREDENTIAL: &str = "zhihu_cookie";
+
+impl Auth {
+    pub fn cookie(&self) -> Fallible<String> {
+        if let Some(ref cookie) = self.cookie {
+            secret::register(cookie);
+            return Ok(cookie.clone());
+        }
+        if let Some(ref path) = self.cookie_file {
+            return secret::read_file(path);
+        }
+        secret::credential(COOKIE_CREDENTIAL)?
+            .ok_or_else(|| err_msg("必须通过`cookie`、`cookie_file`、环境变量或systemd凭据提供知乎Cookie"))
+    }
+
+    // Check the cookie without touching the jar
+    pub fn check(&self) -> Fallible<()> {
+        Client::build(&self.cookie()?).map(drop)
+    }
+}
+
+// Never print the cookie
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("cookie", &self.cookie.as_ref().map(|_| "******"))
+            .field("cookie_file", &self.cookie_file)
+            .field("jar", &self.jar)
+            .finish()
+    }
+}
+
+// [tags]
+// topics = { 'math.LO' = { id = '19554091', name = '数理逻辑' } }
+//
+// [[tags.mention]]   # mention the user when any author or keyword appears
+// hash = '0123456789abcdef0123456789abcdef'
+// name = '某某'
+// authors = ['Saharon Shelah']
+// keywords = ['large cardinal']
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Tags {
+    #[serde(default)]
+    topics: BTreeMap<String, Topic>,
+    #[serde(default)]
+    mention: Vec<Mention>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Topic {
+    id: String,
+    name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Mention {
+    hash: String,
+    name: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+}
+
+impl Tags {
+    pub fn check(&self) -> Fallible<()> {
+        for (sub, topic) in &self.topics {
+            Subject::from_str(sub)?;
+            ensure!(topic.id.chars().all(|c| c.is_ascii_digit()), "无效的话题ID：{}", topic.id);
+        }
+        for m in &self.mention {
+            ensure!(!m.authors.is_empty() || !m.keywords.is_empty(), "提及{}时必须指定`authors`或`keywords`", m.name);
+            for a in &m.authors {
+                Author::from_str(a)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Topics bound to the subjects of the paper, the primary one first
+    fn topics(&self, md: &MetaData) -> Vec<&Topic> {
+        let mut topics = Vec::new();
+        for sub in std::iter::once(&md.prim_sub).chain(md.sub.iter()) {
+            if let Some(t) = self.topics.get(sub.as_str()) {
+                if !topics.iter().any(|u: &&Topic| u.id == t.id) {
+                    topics.push(t);
+                }
+            }
+        }
+        topics
+    }
+
+    // Users to mention for the paper, by its authors or by keywords in the
+    // title or the abstract
+    fn mentions(&self, md: &MetaData) -> Vec<&Mention> {
+        let auths = md.auth
+            .iter()
+            .filter_map(|a| Author::from_str(a).ok())
+            .collect::<Vec<_>>();
+        let text = format!("{}\n{}", md.title, md.abs).to_lowercase();
+
+        self.mention
+            .iter()
+            .filter(|m| {
+                m.authors
+                    .iter()
+                    .filter_map(|a| Author::from_str(a).ok())
+                    .any(|a| auths.iter().any(|b| a.matches(b)))
+                || m.keywords.iter().any(|k| text.contains(&k.to_lowercase()))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct Zhihu {
-    client: Client
+    session: Session,
+    tags: Tags,
+    images: Images,
 }
 
 impl Publisher for Zhihu {
     type Auth = Auth;
     type Error = Error;
     type Handle = Pin;
+    type Article = Article;
 
     fn new(auth: Self::Auth) -> Fallible<Self> {
-        Ok(Zhihu { client: Client::build(&auth.cookie)? })
+        Ok(Zhihu {
+            session: Session::new(&auth.cookie()?, auth.jar.as_deref())?,
+            tags: Tags::default(),
+            images: Images::default(),
+        })
+    }
+
+    fn publish(&self, md: &MetaData, extra: &Extra, refs: Option<Pin>) -> Fallible<Pin> {
+        let state = self.session.state()?;
+        ensure!(state == SessionState::Valid, "无法发布：{}", state);
+
+        // A repost only needs a line of text, the content is in the original pin
+        let content = if refs.is_some() {
+            List::new().text(&headline(md))
+        } else {
+            format(md, extra, &self.tags, &self.images, &self.session)?
+        };
+
+        let refs = refs.unwrap_or_default();
+        Pin::create(content, refs, &self.session)
     }
 
-    fn publish(&self, md: &MetaData, refs: Option<Pin>) -> Fallible<Pin> {
-        ensure!(self.client.is_valid()?, "Cookie out of date");
+    fn revise(&self, md: &MetaData, rev: &Revision, orig: Pin) -> Fallible<Pin> {
+        let state = self.session.state()?;
+        ensure!(state == SessionState::Valid, "无法发布：{}", state);
+
+        let mut changes = Vec::new();
+        if let Some(ref title) = rev.title {
+            changes.push(format!("标题更改为：{}", escape(title)));
+        }
+        if !rev.added.is_empty() {
+            changes.push(format!("新增作者：{}", escape(&rev.added.join(", "))));
+        }
+        if !rev.removed.is_empty() {
+            changes.push(format!("移除作者：{}", escape(&rev.removed.join(", "))));
+        }
+        if rev.abs > 0.0 {
+            changes.push(format!("摘要改动约{:.0}%", rev.abs * 100.0));
+        }
 
-        let refs = refs.unwrap_or_else(Pin::default);
-        Ok(Pin::create(format(&md, &self.client)?, refs, &self.client)?)
+        let url: Url = (&md.index).into();
+        let content = List::new()
+            .text(&format!("<p>[更新] {}</p><p>{}</p>", strip_p(&headline(md)), changes.join("<br>")))
+            .link(url.as_str(), &self.session)?;
+        Pin::create(content, orig, &self.session)
+    }
+
+    fn state(&self) -> Fallible<SessionState> {
+        self.session.state()
+    }
+
+    fn expires(&self) -> Option<DateTime<Utc>> {
+        self.session.expires()
+    }
+
+    fn fetch(&self, pin: &Pin) -> Fallible<PinState> {
+        pin.fetch(&self.session)
+    }
+
+    fn delete(&self, pin: &Pin) -> Fallible<()> {
+        pin.delete(&self.session)
+    }
+
+    fn edit(&self, pin: &Pin, md: &MetaData, extra: &Extra, text: Option<&str>) -> Fallible<()> {
+        let content = match text {
+            Some(text) => List::new().text(&format!("<p>{}</p>", escape(text))),
+            None => format(md, extra, &self.tags, &self.images, &self.session)?,
+        };
+        pin.edit(content, &self.session)
+    }
+
+    fn digest(&self, sub: &Subject, mds: &[MetaData]) -> Fallible<Pin> {
+        let state = self.session.state()?;
+        ensure!(state == SessionState::Valid, "无法发布：{}", state);
+
+        let items = mds
+            .iter()
+            .map(|md| {
+                let url: Url = (&md.index).into();
+                format!("{}<br>{}", strip_p(&headline(md)), url)
+            })
+            .collect::<Vec<_>>();
+        let content = List::new()
+            .text(&format!("<p>{}的其他{}篇新论文：</p><p>{}</p>", sub, mds.len(), items.join("<br><br>")));
+        Pin::create(content, Pin::default(), &self.session)
+    }
+
+    fn article(&self, title: &str, groups: &[(Subject, Vec<MetaData>)], column: &str, draft: bool) -> Fallible<Article> {
+        let state = self.session.state()?;
+        ensure!(state == SessionState::Valid, "无法发布：{}", state);
+
+        let mut html = String::new();
+        for (sub, mds) in groups {
+            html.push_str(&format!("<h2>{}</h2>", sub));
+            for md in mds {
+                let url: Url = (&md.index).into();
+                html.push_str(&headline(md));
+                html.push_str(&crate::zhihu_api::article::image(render(md)?.as_str(), &self.session)?);
+                html.push_str(&format!(r#"<p><a href="{0}">{0}</a></p>"#, url));
+            }
+        }
+
+        let art = Article::draft(title, &html, &self.session)?;
+        if !draft {
+            art.publish(column, &self.session)?;
+        }
+        Ok(art)
     }
 }
 
 // TODO: support mutli-backend publishing
-pub fn init(env: &Env) -> Fallible<Zhihu> {
-    let auth = toml::from_str(env
-        .get(AUTH_KEY)
-        .ok_or_else(|| err_msg("必须指定至少一种授权方式（Cookie或密码）"))?
-        .to_string()
-        .as_str())?;
-    Zhihu::new(auth)
+pub fn init(auth: &Auth, tags: &Tags, images: &Images) -> Fallible<Zhihu> {
+    Ok(Zhihu {
+        tags: tags.clone(),
+        images: images.clone(),
+        ..Zhihu::new(auth.clone())?
+    })
 }
 
 fn render(md: &MetaData) -> Fallible<Url> {
@@ -100,7 +356,7 @@ fn render(md: &MetaData) -> Fallible<Url> {
         resp
             .text()?
             .split(' ')
-            .nth(0)
+            .next()
             .ok_or_else(|| err_msg("LaTeX图片生成API格式错误"))?
             .split('\n')
             .nth(1)
@@ -108,17 +364,62 @@ fn render(md: &MetaData) -> Fallible<Url> {
     )?)
 }
 
-fn format(md: &MetaData, client: &Client) -> Fallible<List> {
+fn headline(md: &MetaData) -> String {
     let authors = if md.auth.len() > 2 {
         format!("{} et. al.", md.auth[0])
     } else {
         md.auth.join(", ")
     };
 
+    format!("<p>{}: {}</p>", escape(&authors), escape(&md.title))
+}
+
+// Titles like `$a<b$` aren't markup
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn strip_p(html: &str) -> &str {
+    html.trim_start_matches("<p>").trim_end_matches("</p>")
+}
+
+// The TL;DR and translations go below the headline, the image keeps the original
+fn extras(extra: &Extra) -> String {
+    let mut html = String::new();
+    if let Some(ref tldr) = extra.tldr {
+        html.push_str(&format!("<p>TL;DR：{}</p>", escape(tldr)));
+    }
+    if let Some(ref title) = extra.title {
+        html.push_str(&format!("<p>标题：{}</p>", escape(title)));
+    }
+    if let Some(ref abs) = extra.abs {
+        html.push_str(&format!("<p>摘要：{}</p>", escape(abs)));
+    }
+    html
+}
+
+fn format(md: &MetaData, extra: &Extra, tags: &Tags, images: &Images, session: &Session) -> Fallible<List> {
     let url: Url = (&md.index).into();
-    // TODO: configurable temporarily image path
-    List::new()
-        .text(&format!("<p>{}: {}</p>", authors, md.title))
-        .link(url.as_str(), client)?
-        .image_url("/tmp/temp.pngg", render(md)?.as_str(), client)
+    let mut list = List::new()
+        .text(&format!("{}{}", headline(md), extras(extra)))
+        .link(url.as_str(), session)?
+        .image_url(render(md)?.as_str(), session)?;
+
+    if images.enabled() {
+        // Optional as well, a failed upload only loses the image
+        for bytes in images.collect(md) {
+            match List::new().image(bytes, session) {
+                Ok(image) => list.extend(image),
+                Err(e) => warn!("上传{}的图片失败：{}", md.index, e),
+            }
+        }
+    }
+
+    for t in tags.topics(md) {
+        list = list.topic(&t.id, &t.name);
+    }
+    for m in tags.mentions(md) {
+        list = list.mention(&m.hash, &m.name);
+    }
+    Ok(list)
 }
@@ -0,0 +1,159 @@
+use super::prelude::*;
+use chrono_tz::Tz;
+
+// [queue]
+// pace = 00:10:00          # at least this long between two posts
+// quiet_from = 23:00:00    # nothing is posted from this time of day
+// quiet_until = 07:00:00   # until this one
+// timezone = 'Asia/Shanghai'
+// max_drain = 01:00:00     # time spent posting per run, the rest goes out in the next one
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QueueConfig {
+    #[schemars(with = "Option<String>")]
+    pace: Option<toml::value::Datetime>,
+    #[schemars(with = "Option<String>")]
+    quiet_from: Option<toml::value::Datetime>,
+    #[schemars(with = "Option<String>")]
+    quiet_until: Option<toml::value::Datetime>,
+    timezone: Option<String>,
+    #[schemars(with = "Option<String>")]
+    max_drain: Option<toml::value::Datetime>,
+}
+
+// Drips the queued posts out, see `pending`. The queue lives in the database,
+// so that it survives restarts.
+pub struct Queue {
+    pace: Duration,
+    quiet: Option<(NaiveTime, NaiveTime)>,
+    tz: Tz,
+    max_drain: Duration,
+}
+
+impl Queue {
+    // Until when a run may post, see `wait`
+    pub fn deadline(&self) -> DateTime<Utc> {
+        Utc::now() + self.max_drain
+    }
+
+    // Wait until the next post may go out, returns false if that's after the
+    // deadline or the wait is interrupted, i.e. terminated or asked to reload.
+    // The rest of the queue is posted by the next run then.
+    pub fn wait(&self, conn: SqlConn, inst: &str, sig: &Signals, deadline: DateTime<Utc>) -> Fallible<bool> {
+        let now = Utc::now();
+        let at = pin::last_posted(conn, inst)?.map_or(now, |t| (t + self.pace).max(now));
+        let at = self.after_quiet(at);
+        if at > deadline {
+            info!("下一条想法将于{}发布，留待之后的运行", at.with_timezone(&chrono::Local));
+            return Ok(false);
+        }
+
+        match (at - now).to_std() {
+            Ok(wait) if wait.as_secs() > 0 => {
+                info!("下一条想法将于{}发布", at.with_timezone(&chrono::Local));
+                Ok(sig.idle(wait))
+            },
+            _ => Ok(true),
+        }
+    }
+
+    // The end of the quiet hours if `at` is within them
+    fn after_quiet(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let (from, until) = match self.quiet {
+            Some(q) => q,
+            None => return at,
+        };
+
+        let local = at.with_timezone(&self.tz);
+        let time = local.time();
+        let quiet = if from <= until {
+            time >= from && time < until
+        } else {
+            // Over midnight, e.g. 23:00 to 07:00
+            time >= from || time < until
+        };
+        if !quiet {
+            return at;
+        }
+
+        let day = if time >= until {
+            local.date_naive() + Duration::days(1)
+        } else {
+            local.date_naive()
+        };
+        self.tz
+            .from_local_datetime(&day.and_time(until))
+            .earliest()
+            .map_or(at, |t| t.with_timezone(&Utc))
+    }
+}
+
+fn time_of_day(tm: &toml::value::Datetime) -> Fallible<NaiveTime> {
+    Ok(NaiveTime::parse_from_str(tm.to_string().as_str(), "%H:%M:%S")?)
+}
+
+pub fn init(conf: &QueueConfig) -> Fallible<Queue> {
+    let quiet = match (&conf.quiet_from, &conf.quiet_until) {
+        (Some(from), Some(until)) => Some((time_of_day(from)?, time_of_day(until)?)),
+        (None, None) => None,
+        _ => bail!("`quiet_from`与`quiet_until`必须同时指定"),
+    };
+
+    Ok(Queue {
+        pace: match conf.pace {
+            Some(ref p) => Duration::from_std(toml_duration(p)?)?,
+            None => Duration::zero(),
+        },
+        quiet,
+        tz: conf.timezone
+            .as_ref()
+            .map_or(Ok(Tz::UTC), |tz| tz.parse::<Tz>())
+            .map_err(err_msg)?,
+        max_drain: match conf.max_drain {
+            Some(ref d) => Duration::from_std(toml_duration(d)?)?,
+            None => Duration::hours(1),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(conf: &str) -> Queue {
+        init(&toml::from_str::<QueueConfig>(conf).unwrap()).unwrap()
+    }
+
+    fn utc(tm: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(tm, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    #[test]
+    fn over_midnight() {
+        // 23:00 to 07:00 in UTC+8, i.e. 15:00 to 23:00 UTC
+        let q = queue("quiet_from = 23:00:00\nquiet_until = 07:00:00\ntimezone = 'Asia/Shanghai'");
+        assert_eq!(q.after_quiet(utc("2019-12-20 14:59")), utc("2019-12-20 14:59"));
+        // Before midnight, until the next morning
+        assert_eq!(q.after_quiet(utc("2019-12-20 15:00")), utc("2019-12-20 23:00"));
+        // After midnight, until the same morning
+        assert_eq!(q.after_quiet(utc("2019-12-20 17:30")), utc("2019-12-20 23:00"));
+        assert_eq!(q.after_quiet(utc("2019-12-20 23:00")), utc("2019-12-20 23:00"));
+    }
+
+    #[test]
+    fn within_day() {
+        let q = queue("quiet_from = 12:00:00\nquiet_until = 14:00:00");
+        assert_eq!(q.after_quiet(utc("2019-12-20 11:59")), utc("2019-12-20 11:59"));
+        assert_eq!(q.after_quiet(utc("2019-12-20 13:00")), utc("2019-12-20 14:00"));
+        assert_eq!(q.after_quiet(utc("2019-12-20 14:00")), utc("2019-12-20 14:00"));
+
+        let q = queue("");
+        assert_eq!(q.after_quiet(utc("2019-12-20 13:00")), utc("2019-12-20 13:00"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(init(&toml::from_str::<QueueConfig>("quiet_from = 23:00:00").unwrap()).is_err());
+        assert!(init(&toml::from_str::<QueueConfig>("timezone = 'Mars/Olympus'").unwrap()).is_err());
+    }
+}
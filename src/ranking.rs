@@ -0,0 +1,222 @@
+use super::prelude::*;
+use std::collections::BTreeMap;
+
+// [ranking]
+// enabled = true
+// top = 10                 # papers posted alone per run, the rest go into a digest of their subject
+// keywords = { 'forcing' = 2.0, 'large cardinal' = 3.0 }   # counted twice in the title
+// authors = ['Saharon Shelah']
+// author_weight = 5.0      # per followed author
+// cross_list_weight = -0.5 # per subject besides the primary one
+// length_weight = 0.5      # per 1000 characters of the abstract, up to 2000
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RankingConfig {
+    #[serde(default)]
+    enabled: bool,
+    top: Option<usize>,
+    #[serde(default)]
+    keywords: BTreeMap<String, f64>,
+    #[serde(default)]
+    authors: Vec<String>,
+    author_weight: Option<f64>,
+    cross_list_weight: Option<f64>,
+    length_weight: Option<f64>,
+}
+
+// Each part of the score is kept for tuning the weights
+#[derive(Clone, Debug, Default)]
+pub struct Score {
+    pub keywords: f64,
+    pub followed: f64,
+    pub cross_list: f64,
+    pub length: f64,
+    pub total: f64,
+}
+
+pub struct Ranking {
+    enabled: bool,
+    top: usize,
+    keywords: Vec<(String, f64)>,
+    authors: Vec<Author>,
+    author_weight: f64,
+    cross_list_weight: f64,
+    length_weight: f64,
+}
+
+impl Ranking {
+    pub fn score(&self, md: &MetaData) -> Score {
+        let title = md.title.to_lowercase();
+        let abs = md.abs.to_lowercase();
+        let keywords = self.keywords
+            .iter()
+            .map(|(k, w)| w * (2 * title.matches(k.as_str()).count() + abs.matches(k.as_str()).count()) as f64)
+            .sum::<f64>();
+
+        let auths = md.auth
+            .iter()
+            .filter_map(|a| Author::from_str(a).ok())
+            .collect::<Vec<_>>();
+        let followed = self.author_weight * self.authors
+            .iter()
+            .filter(|a| auths.iter().any(|b| a.matches(b)))
+            .count() as f64;
+
+        let cross = md.sub.iter().filter(|s| **s != md.prim_sub).count();
+        let cross_list = self.cross_list_weight * cross as f64;
+        let length = self.length_weight * md.abs.chars().count().min(2000) as f64 / 1000.0;
+
+        Score {
+            keywords,
+            followed,
+            cross_list,
+            length,
+            total: keywords + followed + cross_list + length,
+        }
+    }
+
+    // Keep the score of a paper, for tuning the weights
+    pub fn record(&self, conn: SqlConn, md: &MetaData, sub: &Subject) -> Fallible<()> {
+        if self.enabled {
+            score::insert(conn, &md.index, sub, &self.score(md))?;
+        }
+        Ok(())
+    }
+
+    // Split the new papers of a run into those posted alone and those folded
+    // into a digest, the best first. Nothing is folded unless enabled.
+    pub fn split(&self, mds: Vec<MetaData>) -> (Vec<MetaData>, Vec<MetaData>) {
+        if !self.enabled {
+            return (mds, Vec::new());
+        }
+
+        let mut scored = mds
+            .into_iter()
+            .map(|md| (self.score(&md).total, md))
+            .collect::<Vec<_>>();
+        // Ties keep the order of the feeds
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut top = scored.into_iter().map(|(_, md)| md).collect::<Vec<_>>();
+        let rest = if top.len() > self.top {
+            top.split_off(self.top)
+        } else {
+            Vec::new()
+        };
+        (top, rest)
+    }
+
+    // Post the papers folded by this or earlier runs, one digest for each
+    // subject. The papers refer to the digest by their scores, it's no pin of
+    // theirs. Every digest waits for the queue first, see `queue::Queue::wait`,
+    // returns false if one is left for the next run.
+    pub fn digest<P, W>(&self, conn: SqlConn, publisher: &P, inst: &str, mut wait: W) -> Fallible<bool> where
+        P: Publisher<Handle = Pin, Error = Error>,
+        W: FnMut() -> Fallible<bool>,
+    {
+        let mut groups: Vec<(Subject, Vec<MetaData>)> = Vec::new();
+        for (ix, sub) in score::folded(conn, inst)? {
+            let md = paper::by_id(conn, ix)?;
+            match groups.iter_mut().find(|(s, _)| *s == sub) {
+                Some((_, mds)) => mds.push(md),
+                None => groups.push((sub, vec![md])),
+            }
+        }
+
+        for (sub, mds) in groups {
+            if !wait()? {
+                return Ok(false);
+            }
+            let pin = publisher.digest(&sub, &mds)?;
+            info!("{}的其他{}篇论文已合并为想法{}", sub, mds.len(), pin.id);
+            digest::insert(conn, &pin, inst, &sub)?;
+            for md in &mds {
+                score::set_digest(conn, &md.index, &sub, &pin)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+pub fn init(conf: &RankingConfig) -> Fallible<Ranking> {
+    Ok(Ranking {
+        enabled: conf.enabled,
+        top: conf.top.unwrap_or(10),
+        keywords: conf.keywords
+            .iter()
+            .map(|(k, w)| (k.to_lowercase(), *w))
+            .collect(),
+        authors: conf.authors
+            .iter()
+            .map(|a| Author::from_str(a))
+            .try_fold(Vec::new(), try_fold_helper)?,
+        author_weight: conf.author_weight.unwrap_or(5.0),
+        cross_list_weight: conf.cross_list_weight.unwrap_or(-0.5),
+        length_weight: conf.length_weight.unwrap_or(0.5),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paper(id: &str, title: &str, auth: &[&str], abs: &str, sub: &[Subject]) -> MetaData {
+        MetaData {
+            index: Index::from_str(id).unwrap(),
+            title: title.to_owned(),
+            auth: auth.iter().map(|a| a.to_string()).collect(),
+            affil: auth.iter().map(|_| None).collect(),
+            abs: abs.to_owned(),
+            sub: sub.to_vec(),
+            prim_sub: Subject::math_LO,
+        }
+    }
+
+    fn ranking(conf: &str) -> Ranking {
+        init(&toml::from_str::<RankingConfig>(conf).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn score() {
+        let rk = ranking("enabled = true\nkeywords = { 'Forcing' = 2.0 }\nauthors = ['Saharon Shelah']");
+        let md = paper(
+            "1901.00001",
+            "Forcing and forcing axioms",
+            &["S. Shelah", "John Smith"],
+            &"forcing ".repeat(500),
+            &[Subject::math_LO, Subject::cs_LO],
+        );
+
+        let s = rk.score(&md);
+        assert_eq!(s.keywords, 2.0 * (2.0 * 2.0 + 500.0));
+        assert_eq!(s.followed, 5.0);
+        assert_eq!(s.cross_list, -0.5);
+        // Up to 2000 characters
+        assert_eq!(s.length, 1.0);
+        assert_eq!(s.total, s.keywords + s.followed + s.cross_list + s.length);
+    }
+
+    #[test]
+    fn split() {
+        let mds = vec![
+            paper("1901.00001", "Forcing", &[], "", &[]),
+            paper("1901.00002", "Topology", &[], "", &[]),
+            paper("1901.00003", "Forcing forcing", &[], "", &[]),
+            paper("1901.00004", "Algebra", &[], "", &[]),
+        ];
+        let ids = |mds: &[MetaData]| mds.iter().map(|md| md.index.to_string()).collect::<Vec<_>>();
+
+        let (top, rest) = ranking("enabled = true\ntop = 2\nkeywords = { 'forcing' = 1.0 }").split(mds.clone());
+        assert_eq!(ids(&top), vec!["1901.00003", "1901.00001"]);
+        // Ties keep their order
+        assert_eq!(ids(&rest), vec!["1901.00002", "1901.00004"]);
+
+        let (top, rest) = ranking("top = 2").split(mds.clone());
+        assert_eq!(ids(&top), ids(&mds));
+        assert!(rest.is_empty());
+
+        let (top, rest) = ranking("enabled = true").split(mds.clone());
+        assert_eq!(top.len(), 4);
+        assert!(rest.is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use super::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// What the engagement is summed up by
+#[derive(Clone, Copy, Debug)]
+pub enum By {
+    Paper,
+    Subject,
+    Author,
+}
+
+impl FromStr for By {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Fallible<Self> {
+        match s {
+            "paper" => Ok(By::Paper),
+            "subject" => Ok(By::Subject),
+            "author" => Ok(By::Author),
+            _ => bail!("无效的统计方式：{}（可选paper、subject、author）", s),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Fallible<Self> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => bail!("无效的输出格式：{}（可选csv、json）", s),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Row {
+    pub key: String,
+    pub name: String,
+    pub pins: usize,
+    pub likes: i64,
+    pub comments: i64,
+    pub reposts: i64,
+    pub score: i64,
+}
+
+// Rank by the latest engagement of the pins posted in [since, until), counting
+// the original pin of a paper together with its reposts. A digest isn't split
+// across its papers, it's an entry of its own unless summed up by subject.
+pub fn rank(
+    conn: SqlConn,
+    by: By,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+) -> Fallible<Vec<Row>> {
+    let mut rows: HashMap<String, Row> = HashMap::new();
+    let mut papers: HashMap<String, MetaData> = HashMap::new();
+
+    for (ix, _, likes, comments, reposts) in stats::latest(conn, since, until)? {
+        if !papers.contains_key(&ix) {
+            let md = paper::by_id(conn, Index::from_str(&ix)?)?;
+            papers.insert(ix.clone(), md);
+        }
+        let md = &papers[&ix];
+
+        let keys = match by {
+            By::Paper => vec![(ix.clone(), md.title.clone())],
+            By::Subject => vec![(md.prim_sub.to_string(), md.prim_sub.to_string())],
+            // Variants of a name are summed up under its normalised form
+            By::Author => md.auth
+                .iter()
+                .map(|a| (Author::lenient(a).key(), a.clone()))
+                .collect(),
+        };
+        for (key, name) in keys {
+            add(&mut rows, key, name, likes, comments, reposts);
+        }
+    }
+
+    for (sub, pid, likes, comments, reposts) in stats::digests(conn, since, until)? {
+        match by {
+            By::Subject => add(&mut rows, sub.clone(), sub, likes, comments, reposts),
+            By::Paper | By::Author => {
+                add(&mut rows, format!("digest:{}", pid), format!("{}合并的想法", sub), likes, comments, reposts)
+            },
+        }
+    }
+
+    let mut rows = rows.into_values().collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.key.cmp(&b.key)));
+    Ok(rows)
+}
+
+fn add(rows: &mut HashMap<String, Row>, key: String, name: String, likes: i64, comments: i64, reposts: i64) {
+    let row = rows.entry(key.clone()).or_insert_with(|| Row { key, name, ..Row::default() });
+    row.pins += 1;
+    row.likes += likes;
+    row.comments += comments;
+    row.reposts += reposts;
+    row.score += likes + comments + reposts;
+}
+
+pub fn render(rows: &[Row], fmt: Format) -> Fallible<String> {
+    match fmt {
+        Format::Json => Ok(serde_json::to_string_pretty(rows)?),
+        Format::Csv => {
+            let mut out = String::from("key,name,pins,likes,comments,reposts,score\n");
+            for r in rows {
+                out.push_str(&format!("{},{},{},{},{},{},{}\n",
+                                      csv(&r.key), csv(&r.name), r.pins, r.likes, r.comments, r.reposts, r.score));
+            }
+            Ok(out)
+        },
+    }
+}
+
+// Quote a field only when needed, doubling the quotes in it
+fn csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
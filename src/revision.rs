@@ -0,0 +1,165 @@
+use super::prelude::*;
+
+// Changes between the stored metadata of a paper and its new version
+#[derive(Debug)]
+pub struct Revision {
+    pub title: Option<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // Ratio of changed words in the abstract, from 0 to 1
+    pub abs: f64,
+}
+
+impl Revision {
+    pub fn diff(old: &MetaData, new: &MetaData) -> Self {
+        let title = if old.title.trim() != new.title.trim() {
+            Some(new.title.clone())
+        } else {
+            None
+        };
+
+        // Spelling out an initial or reordering the name is no change
+        let (olds, news) = (authors(&old.auth), authors(&new.auth));
+        let added = new.auth
+            .iter()
+            .zip(&news)
+            .filter(|(_, a)| !olds.iter().any(|b| a.matches(b)))
+            .map(|(raw, _)| raw.clone())
+            .collect();
+        let removed = old.auth
+            .iter()
+            .zip(&olds)
+            .filter(|(_, a)| !news.iter().any(|b| a.matches(b)))
+            .map(|(raw, _)| raw.clone())
+            .collect();
+
+        Revision { title, added, removed, abs: changed(&old.abs, &new.abs) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.added.is_empty() && self.removed.is_empty() && self.abs == 0.0
+    }
+}
+
+fn authors(raw: &[String]) -> Vec<Author> {
+    raw.iter().map(|a| Author::lenient(a)).collect()
+}
+
+// 1 - 2 * |LCS| / (|old| + |new|) over words, so rewording a sentence counts
+// but reflowing the lines doesn't
+fn changed(old: &str, new: &str) -> f64 {
+    let old = old.split_whitespace().collect::<Vec<_>>();
+    let new = new.split_whitespace().collect::<Vec<_>>();
+    if old.is_empty() && new.is_empty() {
+        return 0.0;
+    }
+
+    let mut prev = vec![0usize; new.len() + 1];
+    for o in &old {
+        let mut row = vec![0usize; new.len() + 1];
+        for (j, n) in new.iter().enumerate() {
+            row[j + 1] = if o == n {
+                prev[j] + 1
+            } else {
+                row[j].max(prev[j + 1])
+            };
+        }
+        prev = row;
+    }
+
+    1.0 - 2.0 * prev[new.len()] as f64 / (old.len() + new.len()) as f64
+}
+
+// Which revisions deserve an announcement, disabled unless enabled
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Threshold {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_true")]
+    title: bool,
+    #[serde(default = "default_true")]
+    authors: bool,
+    #[serde(default = "default_abstract", rename = "abstract")]
+    abs: f64,
+}
+
+fn default_true() -> bool { true }
+fn default_abstract() -> f64 { 0.2 }
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            title: true,
+            authors: true,
+            abs: default_abstract(),
+        }
+    }
+}
+
+impl Threshold {
+    pub fn significant(&self, rev: &Revision) -> bool {
+        self.enabled && !rev.is_empty() && (
+            (self.title && rev.title.is_some())
+            || (self.authors && !(rev.added.is_empty() && rev.removed.is_empty()))
+            || rev.abs >= self.abs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paper(title: &str, auth: &[&str], abs: &str) -> MetaData {
+        MetaData {
+            index: Index::from_str("1901.00001").unwrap(),
+            title: title.to_owned(),
+            auth: auth.iter().map(|a| a.to_string()).collect(),
+            affil: auth.iter().map(|_| None).collect(),
+            abs: abs.to_owned(),
+            sub: vec![Subject::math_LO],
+            prim_sub: Subject::math_LO,
+        }
+    }
+
+    #[test]
+    fn changed_words() {
+        assert_eq!(changed("", ""), 0.0);
+        assert_eq!(changed("a b c d", "a  b\nc d"), 0.0);
+        assert_eq!(changed("a b c d", "e f g h"), 1.0);
+        assert_eq!(changed("a b c d", "a b c e"), 0.25);
+        assert_eq!(changed("", "a b"), 1.0);
+    }
+
+    #[test]
+    fn diff() {
+        let old = paper("Forcing", &["John Smith", "Ann Lee"], "We prove a theorem.");
+
+        let same = paper(" Forcing ", &["J. Smith", "Lee, Ann"], "We prove\na theorem.");
+        assert!(Revision::diff(&old, &same).is_empty());
+
+        let rev = Revision::diff(&old, &paper("Forcing axioms", &["John Smith", "Bo Chen"], "We prove a lemma."));
+        assert_eq!(rev.title.as_deref(), Some("Forcing axioms"));
+        assert_eq!(rev.added, vec!["Bo Chen".to_owned()]);
+        assert_eq!(rev.removed, vec!["Ann Lee".to_owned()]);
+        assert!(rev.abs > 0.0 && rev.abs < 1.0);
+    }
+
+    #[test]
+    fn significant() {
+        let old = paper("Forcing", &["John Smith"], "a b c d e f g h i j");
+        let minor = Revision::diff(&old, &paper("Forcing", &["John Smith"], "a b c d e f g h i k"));
+        let title = Revision::diff(&old, &paper("Forcing axioms", &["John Smith"], "a b c d e f g h i j"));
+
+        let on = Threshold { enabled: true, ..Threshold::default() };
+        assert!(!on.significant(&minor));
+        assert!(on.significant(&title));
+        assert!(!Threshold::default().significant(&title));
+        // The same without the item and with an empty one
+        assert!(!toml::from_str::<Threshold>("").unwrap().significant(&title));
+        assert!(!Threshold { title: false, ..on.clone() }.significant(&title));
+        assert!(Threshold { abs: 0.05, ..on }.significant(&minor));
+    }
+}
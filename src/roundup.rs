@@ -0,0 +1,132 @@
+use super::prelude::*;
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+// [roundup]
+// enabled = true
+// column = 'c_1234567890'   # the column to publish into
+// draft = true              # leave the article as a draft for review
+// cron = '0 0 12 * * Sun'   # sec min hour day month weekday
+// timezone = 'Asia/Shanghai'
+// days = 7                  # the period covered by an article
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RoundupConfig {
+    #[serde(default)]
+    enabled: bool,
+    column: Option<String>,
+    draft: Option<bool>,
+    cron: Option<String>,
+    timezone: Option<String>,
+    days: Option<i64>,
+}
+
+const DEFAULT_CRON: &str = "0 0 12 * * Sun";
+
+// A weekly column article of the papers posted by the instance
+pub struct Roundup {
+    enabled: bool,
+    column: String,
+    draft: bool,
+    sched: cron::Schedule,
+    tz: Tz,
+    days: i64,
+}
+
+impl Roundup {
+    // Publish the roundup if one is due, i.e. a scheduled time has passed since
+    // the end of the last one. Missed ones are skipped.
+    pub fn run<P>(&self, conn: SqlConn, publisher: &P, inst: &str) -> Fallible<()> where
+        P: Publisher<Article = Article, Error = Error>,
+    {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let last = article::last(conn, inst)?.unwrap_or_else(|| now - Duration::days(self.days));
+        let due = self.sched
+            .after(&last.with_timezone(&self.tz))
+            .take_while(|t| t.with_timezone(&Utc) <= now)
+            .last();
+
+        if let Some(until) = due {
+            let until = until.with_timezone(&Utc);
+            self.publish(conn, publisher, inst, until - Duration::days(self.days), until, self.draft)?;
+        }
+        Ok(())
+    }
+
+    // Publish an article of the papers posted in [since, until), returns None
+    // if there's none
+    pub fn publish<P>(
+        &self,
+        conn: SqlConn,
+        publisher: &P,
+        inst: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        draft: bool,
+    ) -> Fallible<Option<Article>> where
+        P: Publisher<Article = Article, Error = Error>,
+    {
+        let mut groups = BTreeMap::new();
+        for ix in pin::published(conn, inst, since, until)? {
+            let md = paper::by_id(conn, ix)?;
+            groups
+                .entry(md.prim_sub.to_string())
+                .or_insert_with(|| (md.prim_sub.clone(), Vec::new()))
+                .1
+                .push(md);
+        }
+        if groups.is_empty() {
+            info!("{}至{}没有发布的论文，跳过周报", since, until);
+            return Ok(None);
+        }
+        let groups = groups.into_values().collect::<Vec<_>>();
+
+        let title = format!(
+            "arXiv周报（{}至{}）",
+            since.with_timezone(&self.tz).format("%Y-%m-%d"),
+            (until - Duration::seconds(1)).with_timezone(&self.tz).format("%Y-%m-%d"),
+        );
+        let art = publisher.article(&title, &groups, &self.column, draft)?;
+        article::insert(conn, &art, inst, &title, since, until, draft)?;
+
+        if draft {
+            info!("已创建周报草稿：{}", art.url());
+        } else {
+            info!("已发布周报：{}", art.url());
+        }
+        Ok(Some(art))
+    }
+
+    pub fn days(&self) -> i64 {
+        self.days
+    }
+
+    pub fn draft(&self) -> bool {
+        self.draft
+    }
+}
+
+pub fn init(conf: &RoundupConfig) -> Fallible<Roundup> {
+    let expr = conf.cron.as_ref().map_or(DEFAULT_CRON, String::as_str);
+    let days = conf.days.unwrap_or(7);
+    ensure!(days > 0, "`days`必须为正数");
+    ensure!(!conf.enabled || conf.column.is_some(), "启用周报时必须指定`column`");
+
+    Ok(Roundup {
+        enabled: conf.enabled,
+        column: conf.column.clone().unwrap_or_default(),
+        draft: conf.draft.unwrap_or(true),
+        sched: expr
+            .parse::<cron::Schedule>()
+            .map_err(|e| err_msg(format!("无效的cron表达式`{}`：{}", expr, e)))?,
+        tz: conf.timezone
+            .as_ref()
+            .map_or(Ok(Tz::UTC), |tz| tz.parse::<Tz>())
+            .map_err(err_msg)?,
+        days,
+    })
+}
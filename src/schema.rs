@@ -1,7 +1,89 @@
 table! {
-    authors (arxiv_id, auth) {
+    articles (id) {
+        id -> Text,
+        instance -> Text,
+        title -> Text,
+        since -> Text,
+        until -> Text,
+        draft -> Bool,
+        created_at -> Text,
+    }
+}
+
+table! {
+    author_papers (arxiv_id, author_id) {
         arxiv_id -> Text,
+        author_id -> Integer,
+        position -> Integer,
         auth -> Text,
+        affiliation -> Nullable<Text>,
+    }
+}
+
+table! {
+    author_variants (author_id, variant) {
+        author_id -> Integer,
+        variant -> Text,
+    }
+}
+
+table! {
+    author_rekey (author_id) {
+        author_id -> Integer,
+    }
+}
+
+table! {
+    authors (id) {
+        id -> Integer,
+        name -> Text,
+        norm_name -> Text,
+        family -> Text,
+        orcid -> Nullable<Text>,
+        arxiv_auth_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    claims (arxiv_id) {
+        arxiv_id -> Text,
+        instance -> Text,
+        claimed_at -> Text,
+    }
+}
+
+table! {
+    digest_stats (digest_id, recorded_at) {
+        digest_id -> Text,
+        recorded_at -> Text,
+        likes -> BigInt,
+        comments -> BigInt,
+        reposts -> BigInt,
+    }
+}
+
+table! {
+    digests (id) {
+        id -> Text,
+        instance -> Text,
+        sub -> Text,
+        pub_date -> Text,
+        status -> Text,
+        synced_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    instance_subjects (instance, sub) {
+        instance -> Text,
+        sub -> Text,
+    }
+}
+
+table! {
+    instances (name) {
+        name -> Text,
+        heartbeat -> Text,
     }
 }
 
@@ -12,6 +94,40 @@ table! {
         #[sql_name = "abstract"]
         abstract_ -> Text,
         prim_sub -> Text,
+        announced -> Nullable<Text>,
+        tldr -> Nullable<Text>,
+    }
+}
+
+table! {
+    pending (arxiv_id, instance, sub) {
+        arxiv_id -> Text,
+        instance -> Text,
+        sub -> Text,
+        queued_at -> Text,
+        position -> BigInt,
+    }
+}
+
+table! {
+    pending_revisions (arxiv_id, instance) {
+        arxiv_id -> Text,
+        instance -> Text,
+        sub -> Text,
+        title -> Text,
+        auth -> Text,
+        abs -> Text,
+        queued_at -> Text,
+    }
+}
+
+table! {
+    pin_stats (pin_id, recorded_at) {
+        pin_id -> Text,
+        recorded_at -> Text,
+        likes -> BigInt,
+        comments -> BigInt,
+        reposts -> BigInt,
     }
 }
 
@@ -20,7 +136,26 @@ table! {
         id -> Text,
         ref_id -> Nullable<Text>,
         arxiv_id -> Text,
-        pub_date -> Timestamp,
+        pub_date -> Text,
+        instance -> Nullable<Text>,
+        sub -> Nullable<Text>,
+        status -> Text,
+        synced_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    scores (arxiv_id, sub) {
+        arxiv_id -> Text,
+        sub -> Text,
+        keywords -> Double,
+        followed -> Double,
+        cross_list -> Double,
+        length -> Double,
+        total -> Double,
+        digest -> Nullable<Text>,
+        scored_at -> Text,
+        folded_by -> Nullable<Text>,
     }
 }
 
@@ -31,21 +166,59 @@ table! {
     }
 }
 
+table! {
+    translations (arxiv_id, field, lang) {
+        arxiv_id -> Text,
+        field -> Text,
+        lang -> Text,
+        origin -> Text,
+        text -> Text,
+        translated_at -> Text,
+    }
+}
+
 table! {
     update_time (subject) {
         subject -> Text,
-        rss_time -> Timestamp,
+        rss_time -> Text,
     }
 }
 
-joinable!(authors -> papers (arxiv_id));
+// papers_fts differs between backends, see `db::search`
+// Times are stored as text in both backends, see `db::sql_time`
+
+joinable!(author_papers -> authors (author_id));
+joinable!(author_papers -> papers (arxiv_id));
+joinable!(author_rekey -> authors (author_id));
+joinable!(author_variants -> authors (author_id));
+joinable!(digest_stats -> digests (digest_id));
+joinable!(instance_subjects -> instances (instance));
+joinable!(pending -> papers (arxiv_id));
+joinable!(pending_revisions -> papers (arxiv_id));
+joinable!(pin_stats -> pins (pin_id));
 joinable!(pins -> papers (arxiv_id));
+joinable!(scores -> papers (arxiv_id));
 joinable!(subjects -> papers (arxiv_id));
+joinable!(translations -> papers (arxiv_id));
 
 allow_tables_to_appear_in_same_query!(
+    articles,
+    author_papers,
+    author_rekey,
+    author_variants,
     authors,
+    claims,
+    digest_stats,
+    digests,
+    instance_subjects,
+    instances,
     papers,
+    pending,
+    pending_revisions,
+    pin_stats,
     pins,
+    scores,
     subjects,
+    translations,
     update_time,
 );
@@ -1,15 +1,30 @@
 use super::prelude::*;
 
-const SCRAPE_KEY: &str = "scraper";
-
 // TODO: Use &str to avoid clone
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Scraper {
     source: Option<String>,
     subject: Vec<String>,
 }
 
 impl Scraper {
+    pub fn subjects(&self) -> Fallible<Vec<Subject>> {
+        self.subject
+            .iter()
+            .map(|s| Subject::from_str(s.as_str()))
+            .try_fold(Vec::new(), try_fold_helper)
+    }
+
+    pub fn check(&self) -> Fallible<()> {
+        ensure!(!self.subject.is_empty(), "至少需要指定一个订阅的学科");
+        self.subjects()?;
+        match self.source.as_ref().map_or("rss", String::as_str) {
+            "rss" => Ok(()),
+            src => bail!("无效的抓取源：{}", src),
+        }
+    }
+
     pub fn scrape(&self, conn: SqlConn) -> Fallible<Vec<Rss>> {
         let src = self.source
             .clone()
@@ -17,16 +32,19 @@ impl Scraper {
 
         match src.as_str() {
             "rss" => {
-                self.subject
-                    .iter()
-                    .map(|s| Subject::from_str(s.as_str()))
-                    .try_fold(Vec::new(), try_fold_helper)?
+                self.subjects()?
                     .into_iter()
                     .map(Rss::try_from)
                     .try_fold(Vec::new(), |mut acc, rss| {
                         if let Ok(rss) = rss {
-                            if rss.last() > paper::last(conn, &rss.sub)?
-                                .unwrap_or(DateTime::parse_from_rfc3339("1970-01-01T00:00:00-00:00")?) {
+                            // A run stopped halfway leaves some papers of the feed behind
+                            let stale = rss.last() > paper::last(conn, &rss.sub)?
+                                .unwrap_or(DateTime::parse_from_rfc3339("1970-01-01T00:00:00-00:00")?);
+                            if stale || !rss
+                                .index()
+                                .iter()
+                                .map(|ix| paper::exists(conn, ix))
+                                .try_fold(true, |acc, e| e.map(|e| acc && e))? {
                                 acc.push(rss);
                             } else {
                                 info!("本次没有更新，等待下一次轮询");
@@ -42,10 +60,7 @@ impl Scraper {
     }
 }
 
-pub fn init(env: &Env) -> Fallible<Scraper> {
-    Ok(toml::from_str(env
-        .get(SCRAPE_KEY)
-        .ok_or_else(|| err_msg("至少需要指定一个订阅的学科"))?
-        .to_string()
-        .as_str())?)
-}
\ No newline at end of file
+pub fn init(conf: &Scraper) -> Fallible<Scraper> {
+    conf.check()?;
+    Ok(conf.clone())
+}
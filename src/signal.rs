@@ -0,0 +1,92 @@
+use super::prelude::*;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use signal_hook::{SIGHUP, SIGINT, SIGTERM};
+
+// How often a sleeping thread checks the flags
+const TICK_SECS: u64 = 1;
+// How often the config file is checked for modification
+const WATCH_SECS: u64 = 5;
+
+// SIGTERM and SIGINT ask the bot to finish the current paper and exit,
+// SIGHUP asks it to reload the config file after the current run
+#[derive(Clone, Debug)]
+pub struct Signals {
+    term: Arc<AtomicBool>,
+    hup: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn terminated(&self) -> bool {
+        self.term.load(Ordering::SeqCst)
+    }
+
+    pub fn reloading(&self) -> bool {
+        self.hup.load(Ordering::SeqCst)
+    }
+
+    // Whether the running cycle should stop and yield to the main thread
+    pub fn interrupted(&self) -> bool {
+        self.terminated() || self.reloading()
+    }
+
+    // Clear the reload request, returns whether there was one
+    pub fn take_reload(&self) -> bool {
+        self.hup.swap(false, Ordering::SeqCst)
+    }
+
+    // Sleep in short ticks, returns false if terminated before the end
+    pub fn sleep(&self, dur: std::time::Duration) -> bool {
+        self.sleep_while(dur, Self::terminated)
+    }
+
+    // Sleep between two runs, which a reload request interrupts as well
+    pub fn idle(&self, dur: std::time::Duration) -> bool {
+        self.sleep_while(dur, Self::interrupted)
+    }
+
+    // Request a reload whenever the file is modified, until terminated
+    pub fn watch(&self, path: &str) {
+        let sig = self.clone();
+        let path = path.to_owned();
+        let mtime = |p: &str| fs::metadata(p).and_then(|m| m.modified()).ok();
+
+        spawn(move || {
+            let mut last = mtime(&path);
+            while sig.sleep(std::time::Duration::from_secs(WATCH_SECS)) {
+                let now = mtime(&path);
+                if now.is_some() && now != last {
+                    info!("检测到配置文件{}被修改", path);
+                    sig.hup.store(true, Ordering::SeqCst);
+                    last = now;
+                }
+            }
+        });
+    }
+
+    fn sleep_while(&self, dur: std::time::Duration, stop: fn(&Self) -> bool) -> bool {
+        let start = std::time::Instant::now();
+        while !stop(self) {
+            let elapsed = start.elapsed();
+            if elapsed >= dur {
+                return true;
+            }
+            sleep((dur - elapsed).min(std::time::Duration::from_secs(TICK_SECS)));
+        }
+        false
+    }
+}
+
+pub fn init() -> Fallible<Signals> {
+    let sig = Signals {
+        term: Arc::new(AtomicBool::new(false)),
+        hup: Arc::new(AtomicBool::new(false)),
+    };
+
+    signal_hook::flag::register(SIGTERM, Arc::clone(&sig.term))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(&sig.term))?;
+    signal_hook::flag::register(SIGHUP, Arc::clone(&sig.hup))?;
+    Ok(sig)
+}
@@ -0,0 +1,312 @@
+use super::prelude::*;
+use crate::secret;
+use std::collections::HashMap;
+
+// [summary]
+// enabled = true
+// min_length = 600   # only abstracts longer than this many characters
+// sentences = 2      # 1 or 2, picked by the built-in extractive summariser
+// # An OpenAI compatible endpoint instead, the built-in one is used if it fails
+// url = 'https://api.openai.com/v1/chat/completions'
+// model = 'gpt-4o-mini'
+// api_key = '...'
+// prompt = 'Summarise the abstract in one or two sentences.'
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryConfig {
+    #[serde(default)]
+    enabled: bool,
+    min_length: Option<usize>,
+    sentences: Option<usize>,
+    url: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    prompt: Option<String>,
+}
+
+const DEFAULT_PROMPT: &str = "Summarise the abstract of the paper in one or two plain sentences \
+    without formulas. Reply with the summary only.";
+
+// Words too common to tell the sentences apart
+const STOPWORDS: &[&str] = &[
+    "this", "that", "these", "those", "with", "from", "which", "where", "when", "there", "their",
+    "have", "been", "also", "into", "such", "than", "then", "them", "they", "some", "more", "most",
+    "show", "paper", "prove", "result", "results", "give", "given", "using", "used", "over", "under",
+];
+
+pub trait Summariser {
+    fn summarise(&self, md: &MetaData) -> Fallible<String>;
+}
+
+// Picks the sentences sharing the most words with the rest of the abstract and
+// the title, in their original order
+pub struct Extractive {
+    sentences: usize,
+}
+
+impl Extractive {
+    // The first sentences of a summary made elsewhere
+    fn truncate(&self, text: &str) -> String {
+        split(text)
+            .into_iter()
+            .take(self.sentences)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Summariser for Extractive {
+    fn summarise(&self, md: &MetaData) -> Fallible<String> {
+        let sentences = split(&md.abs);
+        ensure!(!sentences.is_empty(), "摘要为空");
+
+        let mut freq = HashMap::new();
+        for w in words(&md.abs) {
+            *freq.entry(w).or_insert(0.0) += 1.0;
+        }
+        for w in words(&md.title) {
+            *freq.entry(w).or_insert(0.0) += 2.0;
+        }
+
+        let mut scored = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let ws = words(s);
+                let score = ws.iter().map(|w| freq.get(w).cloned().unwrap_or(0.0)).sum::<f64>() / (ws.len().max(1) as f64).sqrt();
+                // The opening sentence usually states the topic
+                (if i == 0 { score * 1.2 } else { score }, i)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut picked = scored
+            .into_iter()
+            .take(self.sentences)
+            .map(|(_, i)| i)
+            .collect::<Vec<_>>();
+        picked.sort();
+        Ok(picked
+            .into_iter()
+            .map(|i| sentences[i].as_str())
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+}
+
+// Split at the end of sentences, not inside formulas nor at abbreviations like
+// "e.g." which aren't followed by a capital
+fn split(text: &str) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut math = false;
+    for (i, &c) in chars.iter().enumerate() {
+        current.push(c);
+        if c == '$' {
+            math = !math;
+        }
+        // Full width ones end a sentence anywhere, e.g. in a Chinese reply
+        let end = !math && (['。', '？', '！'].contains(&c) || (c == '.' || c == '?' || c == '!')
+            && chars.get(i + 1).is_none_or(|n| n.is_whitespace())
+            && chars[i + 1..]
+                .iter()
+                .find(|n| !n.is_whitespace())
+                .is_none_or(|n| n.is_uppercase()));
+        if end {
+            sentences.push(current.trim().to_owned());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current.trim().to_owned());
+    }
+    sentences
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-')
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 4 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+// POST {"model", "messages"} to a chat completion endpoint
+pub struct Http {
+    url: Url,
+    model: String,
+    api_key: Option<String>,
+    prompt: String,
+    client: Client,
+}
+
+#[derive(Deserialize)]
+struct RawCompletion {
+    choices: Vec<RawChoice>,
+}
+
+#[derive(Deserialize)]
+struct RawChoice {
+    message: RawMessage,
+}
+
+#[derive(Deserialize)]
+struct RawMessage {
+    content: String,
+}
+
+impl Summariser for Http {
+    fn summarise(&self, md: &MetaData) -> Fallible<String> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": self.prompt },
+                { "role": "user", "content": format!("{}\n\n{}", md.title, md.abs) },
+            ],
+        });
+
+        let mut req = self.client.post(self.url.clone()).json(&body);
+        if let Some(ref key) = self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let mut resp = req.send()?;
+        check_status_code(&mut resp)?;
+
+        let raw: RawCompletion = resp.json()?;
+        let text = raw.choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content.trim().to_owned())
+            .unwrap_or_default();
+        ensure!(!text.is_empty(), "摘要接口返回了空的内容");
+        Ok(text)
+    }
+}
+
+// The summary stage between fetching the metadata and publishing
+pub struct Summary {
+    enabled: bool,
+    min_length: usize,
+    summariser: Option<Box<dyn Summariser + Send>>,
+    fallback: Extractive,
+}
+
+impl Summary {
+    // Fill in the TL;DR of a long abstract. It's stored with the paper and made
+    // again only for another abstract. The built-in one standing in for a failed
+    // endpoint isn't stored, so that the endpoint is tried again next time.
+    pub fn apply(&self, conn: SqlConn, md: &MetaData, extra: &mut Extra) -> Fallible<()> {
+        if !self.enabled || md.abs.chars().count() < self.min_length {
+            return Ok(());
+        }
+        if let Some(text) = paper::summary(conn, &md.index, &md.abs)? {
+            extra.tldr = Some(text);
+            return Ok(());
+        }
+
+        let text = match self.summariser {
+            // Held to the sentences of the built-in one
+            Some(ref s) => match s.summarise(md) {
+                Ok(text) => Some(self.fallback.truncate(&text)),
+                Err(e) => {
+                    warn!("生成{}的摘要失败，改用内置方法：{}", md.index, e);
+                    None
+                },
+            },
+            None => Some(self.fallback.summarise(md)?),
+        };
+        extra.tldr = Some(match text {
+            Some(text) => {
+                paper::set_summary(conn, &md.index, &md.abs, &text)?;
+                text
+            },
+            None => self.fallback.summarise(md)?,
+        });
+        Ok(())
+    }
+}
+
+pub fn init(conf: &SummaryConfig) -> Fallible<Summary> {
+    let sentences = conf.sentences.unwrap_or(2);
+    // More would hardly be shorter than the abstract
+    ensure!((1..=2).contains(&sentences), "`sentences`只能为1或2");
+    if let Some(ref key) = conf.api_key {
+        secret::register(key);
+    }
+
+    let summariser = match conf.url {
+        Some(ref url) => Some(Box::new(Http {
+            url: Url::parse(url).map_err(|_| err_msg("无效的摘要接口地址"))?,
+            model: conf.model
+                .clone()
+                .ok_or_else(|| err_msg("使用摘要接口时必须指定`model`"))?,
+            api_key: conf.api_key.clone(),
+            prompt: conf.prompt.clone().unwrap_or_else(|| DEFAULT_PROMPT.to_owned()),
+            client: Client::new(),
+        }) as Box<dyn Summariser + Send>),
+        None => None,
+    };
+
+    Ok(Summary {
+        enabled: conf.enabled,
+        min_length: conf.min_length.unwrap_or(600),
+        summariser,
+        fallback: Extractive { sentences },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences() {
+        assert_eq!(split("We prove A. Then B follows! Does C hold? Yes"),
+                   vec!["We prove A.", "Then B follows!", "Does C hold?", "Yes"]);
+        // Not at abbreviations nor inside formulas
+        assert_eq!(split("We use e.g. forcing, i.e. a method. It works."),
+                   vec!["We use e.g. forcing, i.e. a method.", "It works."]);
+        assert_eq!(split("Let $x = 1. Y$ be given. Then Z."),
+                   vec!["Let $x = 1. Y$ be given.", "Then Z."]);
+        assert_eq!(split("Version 2.0 is out. See 3.1 Below."),
+                   vec!["Version 2.0 is out.", "See 3.1 Below."]);
+        assert!(split("  ").is_empty());
+        assert_eq!(split("我们证明了A。B随之成立！"), vec!["我们证明了A。", "B随之成立！"]);
+    }
+
+    #[test]
+    fn truncate() {
+        let reply = "We prove A. Then B follows. Also C.";
+        assert_eq!(Extractive { sentences: 2 }.truncate(reply), "We prove A. Then B follows.");
+        assert_eq!(Extractive { sentences: 1 }.truncate(reply), "We prove A.");
+        assert_eq!(Extractive { sentences: 2 }.truncate("Just one"), "Just one");
+    }
+
+    #[test]
+    fn extractive() {
+        let md = MetaData {
+            index: Index::from_str("1901.00001").unwrap(),
+            title: "Forcing axioms and large cardinals".to_owned(),
+            auth: Vec::new(),
+            affil: Vec::new(),
+            abs: "We study forcing axioms. The weather was nice. \
+                  Forcing axioms imply large cardinals in inner models. Coffee helps.".to_owned(),
+            sub: Vec::new(),
+            prim_sub: Subject::math_LO,
+        };
+        let tldr = Extractive { sentences: 2 }.summarise(&md).unwrap();
+        assert_eq!(tldr, "We study forcing axioms. Forcing axioms imply large cardinals in inner models.");
+
+        let empty = MetaData { abs: String::new(), ..md };
+        assert!(Extractive { sentences: 2 }.summarise(&empty).is_err());
+    }
+
+    #[test]
+    fn sentences_range() {
+        let conf = |n| toml::from_str::<SummaryConfig>(&format!("sentences = {}", n)).unwrap();
+        assert!(init(&conf(1)).is_ok());
+        assert!(init(&conf(2)).is_ok());
+        assert!(init(&conf(0)).is_err());
+        assert!(init(&conf(3)).is_err());
+    }
+}
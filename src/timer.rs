@@ -1,37 +1,300 @@
 use super::prelude::*;
+use chrono::{NaiveDate, Weekday, Datelike, Local};
+use chrono_tz::{Tz, America::New_York};
+use rand::Rng;
 
-const TIMER_KEY: &str = "timer";
+const ARXIV_PRESET: &str = "arxiv";
 
-#[derive(Debug, Deserialize)]
-struct Timer {
-    interval: toml::value::Datetime
+// Exactly one of `interval`, `cron` and `preset` should be given, e.g.
+//
+// interval = 08:00:00
+//
+// cron = '0 30 20 * * Sun-Thu'   # sec min hour day month weekday
+// timezone = 'America/New_York'
+//
+// preset = 'arxiv'
+// delay = 00:30:00               # after the announcement
+// holidays = [2026-12-24, 2026-12-31]  # besides `ARXIV_HOLIDAYS`
+// builtin_holidays = true        # false to leave out `ARXIV_HOLIDAYS`, skipping only `holidays`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimerConfig {
+    #[schemars(with = "Option<String>")]
+    interval: Option<toml::value::Datetime>,
+    cron: Option<String>,
+    timezone: Option<String>,
+    preset: Option<String>,
+    #[schemars(with = "Option<String>")]
+    delay: Option<toml::value::Datetime>,
+    #[schemars(with = "Option<Vec<String>>")]
+    holidays: Option<Vec<toml::value::Datetime>>,
+    builtin_holidays: Option<bool>,
+    // Randomly delay every run by up to this long
+    #[schemars(with = "Option<String>")]
+    jitter: Option<toml::value::Datetime>,
 }
 
-impl Default for Timer {
+impl Default for TimerConfig {
     fn default() -> Self {
         Self {
-            interval: "08:00:00".parse().unwrap() // safely unwrap
+            interval: Some("08:00:00".parse().unwrap()), // safely unwrap
+            cron: None,
+            timezone: None,
+            preset: None,
+            delay: None,
+            holidays: None,
+            builtin_holidays: None,
+            jitter: None,
         }
     }
 }
 
-pub fn init<F, T>(env: &Env, f: F) -> Fallible<JoinHandle<T>> where
-    F: FnOnce(std::time::Duration) -> T,
-    F: Send + 'static,
-    T: Send + 'static
-{
-    let itv = env
-        .get(TIMER_KEY)
-        .map_or_else(|| Ok(Timer::default()), |v| toml::from_str(v.to_string().as_str()))?;
+// Days of the year arXiv makes no announcement on, after its holiday schedule.
+// Observed days and closures of a single year go into `holidays`.
+#[derive(Clone, Copy, Debug)]
+enum Holiday {
+    // Month and day
+    Fixed(u32, u32),
+    // The n-th weekday of the month
+    Nth(u32, Weekday, u32),
+}
+
+const ARXIV_HOLIDAYS: &[Holiday] = &[
+    Holiday::Fixed(1, 1),               // New Year's Day
+    Holiday::Fixed(6, 19),              // Juneteenth
+    Holiday::Fixed(7, 4),               // Independence Day
+    Holiday::Nth(11, Weekday::Thu, 4),  // Thanksgiving
+    Holiday::Fixed(12, 25),             // Christmas Day
+];
+
+impl Holiday {
+    fn is(&self, d: NaiveDate) -> bool {
+        match *self {
+            Holiday::Fixed(m, day) => d.month() == m && d.day() == day,
+            Holiday::Nth(m, wd, n) => d.month() == m && d.weekday() == wd && (d.day() - 1) / 7 + 1 == n,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Schedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>, Tz),
+    // arXiv announces new papers at 20:00 US Eastern time from Sunday to Thursday,
+    // except holidays, the built-in ones unless disabled
+    Arxiv { delay: Duration, holidays: Vec<NaiveDate>, builtin: bool },
+}
+
+impl Schedule {
+    // The first run strictly after `after`
+    fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval(itv) => Some(after + *itv),
+            Schedule::Cron(cron, tz) => cron
+                .after(&after.with_timezone(tz))
+                .next()
+                .map(|t| t.with_timezone(&Utc)),
+            Schedule::Arxiv { delay, holidays, builtin } => {
+                // A delay may push the run of the day before past midnight
+                let today = after.with_timezone(&New_York).date_naive();
+                (-1..15)
+                    .map(|d| today + Duration::days(d))
+                    .filter(|d| match d.weekday() {
+                        Weekday::Fri | Weekday::Sat => false,
+                        _ => !(holidays.contains(d) || *builtin && ARXIV_HOLIDAYS.iter().any(|h| h.is(*d))),
+                    })
+                    .filter_map(|d| New_York
+                        .from_local_datetime(&d.and_hms_opt(20, 0, 0)?)
+                        .earliest())
+                    .map(|t| t.with_timezone(&Utc) + *delay)
+                    .find(|t| *t > after)
+            },
+        }
+    }
+}
+
+fn date(d: &toml::value::Datetime) -> Fallible<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(d.to_string().as_str(), "%Y-%m-%d")?)
+}
+
+fn schedule(tm: &TimerConfig) -> Fallible<Schedule> {
+    match (&tm.interval, &tm.cron, &tm.preset) {
+        (Some(itv), None, None) => {
+            // The next run would be the same one again
+            let itv = Duration::from_std(toml_duration(itv)?)?;
+            ensure!(itv > Duration::zero(), "`interval`必须大于0");
+            Ok(Schedule::Interval(itv))
+        },
+        (None, Some(expr), None) => {
+            let tz = tm.timezone
+                .as_ref()
+                .map_or(Ok(Tz::UTC), |tz| tz.parse::<Tz>())
+                .map_err(err_msg)?;
+            let cron = expr.parse::<cron::Schedule>()
+                .map_err(|e| err_msg(format!("无效的cron表达式`{}`：{}", expr, e)))?;
+            Ok(Schedule::Cron(Box::new(cron), tz))
+        },
+        (None, None, Some(preset)) if preset == ARXIV_PRESET => Ok(Schedule::Arxiv {
+            delay: match tm.delay {
+                Some(ref d) => Duration::from_std(toml_duration(d)?)?,
+                None => Duration::zero(),
+            },
+            holidays: tm.holidays
+                .iter()
+                .flatten()
+                .map(date)
+                .try_fold(Vec::new(), try_fold_helper)?,
+            builtin: tm.builtin_holidays.unwrap_or(true),
+        }),
+        (None, None, Some(preset)) => bail!("无效的预设时间表：{}", preset),
+        (None, None, None) => schedule(&TimerConfig::default()),
+        _ => bail!("`interval`、`cron`与`preset`只能指定其中一项"),
+    }
+}
+
+#[derive(Clone)]
+pub struct Timer {
+    sched: Schedule,
+    jitter: std::time::Duration,
+}
+
+impl Timer {
+    // The first run strictly after `at` which is still to come
+    fn upcoming(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = self.sched.next(at);
+        while let Some(at) = next {
+            if at > Utc::now() {
+                break;
+            }
+            next = self.sched.next(at);
+        }
+        next
+    }
+
+    // Run `f` on the schedule in a new thread, which returns the state and the
+    // time of the last run once a signal interrupts it, see `signal`. Pass that
+    // time back as `last` to keep the pace after a reload.
+    pub fn start<B, F>(&self, sig: Signals, last: Option<DateTime<Utc>>, mut bot: B, mut f: F)
+        -> JoinHandle<(B, Option<DateTime<Utc>>)> where
+        B: Send + 'static,
+        F: FnMut(&mut B),
+        F: Send + 'static,
+    {
+        let tm = self.clone();
+
+        // A fixed interval runs at once, then keeps the pace from the start of each
+        // run instead of its end so that it won't drift
+        let first = match (last, &tm.sched) {
+            (Some(at), _) => tm.upcoming(at),
+            (None, Schedule::Interval(_)) => Some(Utc::now()),
+            (None, _) => tm.sched.next(Utc::now()),
+        };
 
-    let dur = NaiveTime::parse_from_str(
-        itv.interval
-            .to_string()
-            .as_str(), "%H:%M:%S"
-    )?
-        .signed_duration_since(NaiveTime::from_hms(0, 0, 0))
-        .to_std()?;
+        spawn(move || {
+            let (mut next, mut last) = (first, last);
+            while let Some(at) = next {
+                let delay = if tm.jitter.as_secs() > 0 {
+                    Duration::seconds(rand::thread_rng().gen_range(0, tm.jitter.as_secs() as i64))
+                } else {
+                    Duration::zero()
+                };
+                if let Ok(wait) = (at + delay - Utc::now()).to_std() {
+                    if !sig.idle(wait) {
+                        return (bot, last);
+                    }
+                }
 
-    let tm = spawn(move || f(dur));
-    Ok(tm)
+                f(&mut bot);
+                last = Some(at);
+                if sig.interrupted() {
+                    return (bot, last);
+                }
+
+                // Skip the runs missed while running
+                next = tm.upcoming(at);
+                if let Some(at) = next {
+                    info!("下一次运行时间：{}", at.with_timezone(&Local));
+                }
+            }
+            error!("时间表中没有下一次运行时间，定时器退出");
+            (bot, last)
+        })
+    }
+}
+
+pub fn init(tm: &TimerConfig) -> Fallible<Timer> {
+    Ok(Timer {
+        sched: schedule(tm)?,
+        jitter: tm.jitter
+            .as_ref()
+            .map_or(Ok(std::time::Duration::from_secs(0)), toml_duration)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sched(conf: &str) -> Schedule {
+        schedule(&toml::from_str::<TimerConfig>(conf).unwrap()).unwrap()
+    }
+
+    fn utc(tm: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(tm, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    #[test]
+    fn arxiv() {
+        let s = sched("preset = 'arxiv'");
+        // Friday, the next announcement is on Sunday 20:00 EST
+        assert_eq!(s.next(utc("2019-12-20 12:00")), Some(utc("2019-12-23 01:00")));
+        // Strictly after
+        assert_eq!(s.next(utc("2019-12-23 01:00")), Some(utc("2019-12-24 01:00")));
+        // 20:00 EDT in summer
+        assert_eq!(s.next(utc("2019-07-01 12:00")), Some(utc("2019-07-02 00:00")));
+
+        let s = sched("preset = 'arxiv'\ndelay = 00:30:00\nholidays = [2019-12-22, 2019-12-23]");
+        assert_eq!(s.next(utc("2019-12-20 12:00")), Some(utc("2019-12-25 01:30")));
+
+        // The run of Monday is on Tuesday 02:00 EST
+        let s = sched("preset = 'arxiv'\ndelay = 06:00:00");
+        assert_eq!(s.next(utc("2019-12-17 06:00")), Some(utc("2019-12-17 07:00")));
+        assert_eq!(s.next(utc("2019-12-17 07:00")), Some(utc("2019-12-18 07:00")));
+    }
+
+    #[test]
+    fn holidays() {
+        let s = sched("preset = 'arxiv'");
+        // Christmas on Thursday, then the weekend
+        assert_eq!(s.next(utc("2025-12-25 01:00")), Some(utc("2025-12-29 01:00")));
+        // Thanksgiving
+        assert_eq!(s.next(utc("2026-11-26 02:00")), Some(utc("2026-11-30 01:00")));
+        assert_eq!(s.next(utc("2026-11-19 02:00")), Some(utc("2026-11-20 01:00")));
+
+        // Added to the built-in ones
+        let s = sched("preset = 'arxiv'\nholidays = [2025-12-24]");
+        assert_eq!(s.next(utc("2025-12-24 02:00")), Some(utc("2025-12-29 01:00")));
+
+        // In place of the built-in ones
+        let s = sched("preset = 'arxiv'\nholidays = [2025-12-24]\nbuiltin_holidays = false");
+        assert_eq!(s.next(utc("2025-12-24 02:00")), Some(utc("2025-12-26 01:00")));
+    }
+
+    #[test]
+    fn cron() {
+        let s = sched("cron = '0 30 20 * * Sun-Thu'\ntimezone = 'America/New_York'");
+        assert_eq!(s.next(utc("2019-12-20 12:00")), Some(utc("2019-12-23 01:30")));
+        assert_eq!(s.next(utc("2019-07-01 12:00")), Some(utc("2019-07-02 00:30")));
+
+        let s = sched("cron = '0 0 8 * * *'");
+        assert_eq!(s.next(utc("2019-12-20 12:00")), Some(utc("2019-12-21 08:00")));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(schedule(&toml::from_str::<TimerConfig>("preset = 'daily'").unwrap()).is_err());
+        assert!(schedule(&toml::from_str::<TimerConfig>("interval = 00:00:00").unwrap()).is_err());
+        assert!(schedule(&toml::from_str::<TimerConfig>("interval = 01:00:00\npreset = 'arxiv'").unwrap()).is_err());
+        assert!(schedule(&toml::from_str::<TimerConfig>("cron = '0 0 8 * * *'\ntimezone = 'Mars/Olympus'").unwrap()).is_err());
+    }
 }
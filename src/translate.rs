@@ -0,0 +1,248 @@
+use super::prelude::*;
+use crate::secret;
+use std::cell::Cell;
+
+// [translate]
+// enabled = true
+// url = 'http://localhost:5000/translate'   # a LibreTranslate compatible endpoint
+// source = 'en'
+// target = 'zh'
+// api_key = '...'                           # if the endpoint needs one
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TranslateConfig {
+    #[serde(default)]
+    enabled: bool,
+    url: Option<String>,
+    source: Option<String>,
+    target: Option<String>,
+    api_key: Option<String>,
+}
+
+pub trait Translator {
+    fn translate(&self, text: &str) -> Fallible<String>;
+    // The target language, translations are cached by it
+    fn lang(&self) -> &str;
+}
+
+// POST {"q", "source", "target", "format", "api_key"}, returns {"translatedText"}
+pub struct Http {
+    url: Url,
+    source: String,
+    target: String,
+    api_key: Option<String>,
+    client: Client,
+}
+
+#[derive(Deserialize)]
+struct RawTranslation {
+    #[serde(rename = "translatedText")]
+    text: String,
+}
+
+impl Translator for Http {
+    fn translate(&self, text: &str) -> Fallible<String> {
+        let mut body = serde_json::json!({
+            "q": text,
+            "source": self.source,
+            "target": self.target,
+            "format": "text",
+        });
+        if let Some(ref key) = self.api_key {
+            body["api_key"] = serde_json::Value::String(key.clone());
+        }
+
+        let mut resp = self.client
+            .post(self.url.clone())
+            .json(&body)
+            .send()?;
+        check_status_code(&mut resp)?;
+
+        Ok(resp.json::<RawTranslation>()?.text)
+    }
+
+    fn lang(&self) -> &str {
+        &self.target
+    }
+}
+
+// The translation stage between fetching the metadata and publishing
+pub struct Translation {
+    translator: Option<Box<dyn Translator + Send>>,
+    // Once the translator fails, only cached translations are taken for the
+    // rest of the run
+    failed: Cell<bool>,
+}
+
+impl Translation {
+    // Try the translator again, at the start of every run
+    pub fn reset(&self) {
+        self.failed.set(false);
+    }
+
+    // Fill in the translated title and abstract. Cached translations are used
+    // unless the original text has changed, e.g. by a new version. The post goes
+    // out untranslated if the translator fails.
+    pub fn apply(&self, conn: SqlConn, md: &MetaData, extra: &mut Extra) -> Fallible<()> {
+        let tr = match self.translator {
+            Some(ref tr) => tr,
+            None => return Ok(()),
+        };
+
+        let failed = &self.failed;
+        let field = |name: &str, origin: &str| -> Fallible<Option<String>> {
+            if let Some(text) = translation::get(conn, &md.index, name, tr.lang(), origin)? {
+                return Ok(Some(text));
+            }
+            if failed.get() {
+                return Ok(None);
+            }
+            match tr.translate(origin) {
+                Ok(text) => {
+                    translation::put(conn, &md.index, name, tr.lang(), origin, &text)?;
+                    Ok(Some(text))
+                },
+                Err(e) => {
+                    warn!("翻译{}失败：{}", md.index, e);
+                    failed.set(true);
+                    Ok(None)
+                },
+            }
+        };
+
+        extra.title = field("title", &md.title)?;
+        extra.abs = field("abs", &md.abs)?;
+        Ok(())
+    }
+}
+
+pub fn init(conf: &TranslateConfig) -> Fallible<Translation> {
+    if !conf.enabled {
+        return Ok(Translation { translator: None, failed: Cell::new(false) });
+    }
+
+    let url = conf.url
+        .as_ref()
+        .ok_or_else(|| err_msg("启用翻译时必须指定`url`"))?;
+    if let Some(ref key) = conf.api_key {
+        secret::register(key);
+    }
+
+    let source = conf.source.clone().unwrap_or_else(|| "en".to_owned());
+    let target = conf.target.clone().unwrap_or_else(|| "zh".to_owned());
+    ensure!(source == "auto" || is_lang(&source), "无效的源语言：{}", source);
+    ensure!(is_lang(&target), "无效的目标语言：{}", target);
+    ensure!(source != target, "源语言与目标语言相同：{}", target);
+
+    Ok(Translation {
+        translator: Some(Box::new(Http {
+            url: Url::parse(url).map_err(|_| err_msg("无效的翻译接口地址"))?,
+            source,
+            target,
+            api_key: conf.api_key.clone(),
+            client: Client::new(),
+        })),
+        failed: Cell::new(false),
+    })
+}
+
+// A language code like `en`, `zh` or `zh-Hans`
+fn is_lang(code: &str) -> bool {
+    let mut parts = code.split('-');
+    let lang = parts.next().unwrap_or_default();
+    lang.len() >= 2 && lang.len() <= 3
+        && lang.chars().all(|c| c.is_ascii_lowercase())
+        && parts.all(|p| !p.is_empty() && p.len() <= 8 && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Conn;
+    use diesel::{Connection, SqliteConnection, connection::SimpleConnection};
+    use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+    fn conf(source: &str, target: &str) -> TranslateConfig {
+        TranslateConfig {
+            enabled: true,
+            url: Some("http://localhost:5000/translate".to_owned()),
+            source: Some(source.to_owned()),
+            target: Some(target.to_owned()),
+            api_key: None,
+        }
+    }
+
+    #[test]
+    fn languages() {
+        assert!(is_lang("en"));
+        assert!(is_lang("zh-Hans"));
+        assert!(is_lang("yue"));
+        assert!(!is_lang("EN"));
+        assert!(!is_lang("e"));
+        assert!(!is_lang("zh-"));
+        assert!(!is_lang("zh_CN"));
+
+        assert!(init(&conf("en", "zh")).is_ok());
+        assert!(init(&conf("auto", "zh-Hant")).is_ok());
+        assert!(init(&conf("en", "auto")).is_err());
+        assert!(init(&conf("zh", "zh")).is_err());
+        assert!(init(&conf("english", "zh")).is_err());
+        assert!(init(&TranslateConfig { url: None, ..conf("en", "zh") }).is_err());
+        // Nothing is checked unless enabled
+        assert!(init(&TranslateConfig { enabled: false, ..conf("zh", "zh") }).is_ok());
+    }
+
+    // Fails on every call, counting them
+    struct Broken(Arc<AtomicUsize>);
+
+    impl Translator for Broken {
+        fn translate(&self, _: &str) -> Fallible<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            bail!("unreachable")
+        }
+
+        fn lang(&self) -> &str {
+            "zh"
+        }
+    }
+
+    #[test]
+    fn cache_only_after_failure() {
+        let c = SqliteConnection::establish(":memory:").unwrap();
+        c.batch_execute(include_str!("../migrations/2026-10-19-190000_translations/up.sql")).unwrap();
+        let conn = Conn::Sqlite(c);
+
+        let md = MetaData {
+            index: Index::from_str("1901.00001").unwrap(),
+            title: "Forcing axioms".to_owned(),
+            auth: Vec::new(),
+            affil: Vec::new(),
+            abs: "We study forcing axioms.".to_owned(),
+            sub: Vec::new(),
+            prim_sub: Subject::math_LO,
+        };
+        translation::put(&conn, &md.index, "abs", "zh", &md.abs, "我们研究力迫公理。").unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let tr = Translation { translator: Some(Box::new(Broken(calls.clone()))), failed: Cell::new(false) };
+        let mut extra = Extra::default();
+        tr.apply(&conn, &md, &mut extra).unwrap();
+
+        // The title fails, the abstract is taken from the cache without a call
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(extra.title, None);
+        assert_eq!(extra.abs.as_deref(), Some("我们研究力迫公理。"));
+
+        // Nor is another paper translated for the rest of the run
+        let md = MetaData { abs: "We study large cardinals.".to_owned(), ..md };
+        let mut extra = Extra::default();
+        tr.apply(&conn, &md, &mut extra).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(extra.abs, None);
+
+        // The next run tries again
+        tr.reset();
+        tr.apply(&conn, &md, &mut extra).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
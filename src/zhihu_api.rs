@@ -1,8 +1,11 @@
 use super::prelude::*;
 
-pub use http::ClientExt;
+pub use http::{ClientExt, SessionState};
 pub use content::{ContentList, List};
+pub use session::Session;
 
 mod http;
 mod content;
+mod session;
 pub mod pin;
+pub mod article;
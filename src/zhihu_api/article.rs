@@ -0,0 +1,50 @@
+use super::*;
+use super::content::{upload_url, r#type::Image};
+use serde_json::json;
+
+// A column (专栏) article
+#[derive(Clone, Debug)]
+pub struct Article {
+    pub id: String,
+}
+
+// Only the fields in use, the others are ignored
+#[derive(Debug, Deserialize)]
+struct RawArticle {
+    id: u64,
+}
+
+impl Article {
+    // Articles are always created as drafts, see `publish`
+    pub fn draft(title: &str, html: &str, session: &Session) -> Fallible<Self> {
+        let mut resp = session.send(session
+            .client()
+            .post("https://zhuanlan.zhihu.com/api/articles/drafts")
+            .json(&json!({ "title": title, "content": html, "delta_time": 0 })))?;
+
+        check_status_code(&mut resp)?;
+
+        let raw: RawArticle = resp.json()?;
+        Ok(Article { id: raw.id.to_string() })
+    }
+
+    // Publish the draft into the column
+    pub fn publish(&self, column: &str, session: &Session) -> Fallible<()> {
+        let mut resp = session.send(session
+            .client()
+            .put(&format!("https://zhuanlan.zhihu.com/api/articles/{}/publish", self.id))
+            .json(&json!({ "column": { "id": column }, "commentPermission": "anyone" })))?;
+
+        check_status_code(&mut resp)
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://zhuanlan.zhihu.com/p/{}", self.id)
+    }
+}
+
+// Upload the image at the url and refer to it in the article
+pub fn image(url: &str, session: &Session) -> Fallible<String> {
+    let Image { url, width, height } = upload_url(url, session)?;
+    Ok(format!(r#"<img src="{}" data-rawwidth="{}" data-rawheight="{}">"#, url, width, height))
+}
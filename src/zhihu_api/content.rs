@@ -1,12 +1,13 @@
 use super::*;
-use reqwest::multipart::Form;
+use reqwest::multipart::{Form, Part};
+use std::io::Read;
 use serde::{
     ser::SerializeStruct,
     Serialize,
     Serializer
 };
 
-mod r#type {
+pub(super) mod r#type {
     use super::*;
 
     #[derive(Debug, Deserialize)]
@@ -25,6 +26,19 @@ mod r#type {
         pub title: String,
         pub image: String,
     }
+
+    #[derive(Debug)]
+    pub struct Topic {
+        pub id: String,
+        pub name: String,
+    }
+
+    // A user is referred to by the hash id in the url of the profile page
+    #[derive(Debug)]
+    pub struct Mention {
+        pub hash: String,
+        pub name: String,
+    }
 }
 
 #[derive(Debug)]
@@ -32,6 +46,8 @@ pub enum Content {
     Text(String),
     Image(r#type::Image),
     Link(r#type::Link),
+    Topic(r#type::Topic),
+    Mention(r#type::Mention),
 }
 
 impl Content {
@@ -39,46 +55,68 @@ impl Content {
         Content::Text(text.to_owned())
     }
 
-    // TODO: use Path instead of &str
-    fn image_path(path: &str, client: &Client) -> Fallible<Self> {
-        let img = Form::new().file("picture", path)?;
+    fn image(bytes: Vec<u8>, session: &Session) -> Fallible<Self> {
+        Ok(Content::Image(upload(bytes, session)?))
+    }
+
+    fn image_url(url: &str, session: &Session) -> Fallible<Self> {
+        Ok(Content::Image(upload_url(url, session)?))
+    }
 
-        let mut resp = client
-            .post("https://www.zhihu.com/api/v4/uploaded_images")
-            .multipart(img)
-            .send()?;
+    fn link(url: &str, session: &Session) -> Fallible<Self> {
+        let mut resp = session.send(session
+            .client()
+            .get("https://www.zhihu.com/api/v3/scraper")
+            .query(&[("url", url), ("image", "1")]))?;
 
         check_status_code(&mut resp)?;
 
-        Ok(Content::Image(resp.json()?))
+        Ok(Content::Link(resp.json()?))
     }
 
-    fn image_url(path: &str, url: &str, client: &Client) -> Fallible<Self> {
-        let mut img = reqwest::get(url)?;
-        check_status_code(&mut img)?;
+    fn topic(id: &str, name: &str) -> Self {
+        Content::Topic(r#type::Topic { id: id.to_owned(), name: name.to_owned() })
+    }
 
-        let mut temp = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)?;
+    fn mention(hash: &str, name: &str) -> Self {
+        Content::Mention(r#type::Mention { hash: hash.to_owned(), name: name.to_owned() })
+    }
+}
 
-        img.copy_to(&mut temp)?;
-        let ret = Self::image_path(path, client)?;
+// Images uploaded to Zhihu can be used in pins as well as articles. Only the
+// bytes are sent, the type is told by their magic number.
+pub(super) fn upload(bytes: Vec<u8>, session: &Session) -> Fallible<r#type::Image> {
+    let (mime, name) = image_type(&bytes)?;
+    let img = Form::new().part("picture", Part::bytes(bytes).file_name(name).mime_str(mime)?);
 
-        fs::remove_file(path)?;
-        Ok(ret)
-    }
+    let mut resp = session.send(session
+        .client()
+        .post("https://www.zhihu.com/api/v4/uploaded_images")
+        .multipart(img))?;
 
-    fn link(url: &str, client: &Client) -> Fallible<Self> {
-        let mut resp = client
-            .get("https://www.zhihu.com/api/v3/scraper")
-            .query(&[("url", url), ("image", "1")])
-            .send()?;
+    check_status_code(&mut resp)?;
 
-        check_status_code(&mut resp)?;
+    Ok(resp.json()?)
+}
 
-        Ok(Content::Link(resp.json()?))
+pub(super) fn upload_url(url: &str, session: &Session) -> Fallible<r#type::Image> {
+    let mut img = reqwest::get(url)?;
+    check_status_code(&mut img)?;
+
+    let mut bytes = Vec::new();
+    img.read_to_end(&mut bytes)?;
+    upload(bytes, session)
+}
+
+fn image_type(bytes: &[u8]) -> Fallible<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok(("image/png", "image.png"))
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Ok(("image/jpeg", "image.jpg"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Ok(("image/gif", "image.gif"))
+    } else {
+        bail!("不支持的图片格式")
     }
 }
 
@@ -111,6 +149,20 @@ impl Serialize for Content {
                 st.serialize_field("imageUrl", &image)?;
                 st.end()
             }
+            Content::Topic(r#type::Topic { id, name }) => {
+                let mut st = serializer.serialize_struct("Content", 3)?;
+                st.serialize_field("type", "topic")?;
+                st.serialize_field("id", &id)?;
+                st.serialize_field("name", &name)?;
+                st.end()
+            }
+            Content::Mention(r#type::Mention { hash, name }) => {
+                let mut st = serializer.serialize_struct("Content", 3)?;
+                st.serialize_field("type", "mention")?;
+                st.serialize_field("member_hash_id", &hash)?;
+                st.serialize_field("name", &name)?;
+                st.end()
+            }
         }
     }
 }
@@ -121,9 +173,11 @@ pub trait ContentList {
     type List;
 
     fn text(self, text: &str) -> Self::List;
-    fn image_path(self, path: &str, client: &Client) -> Fallible<Self::List>;
-    fn image_url(self, path: &str, url: &str, client: &Client) -> Fallible<Self::List>;
-    fn link(self, url: &str, client: &Client) -> Fallible<Self::List>;
+    fn image(self, bytes: Vec<u8>, session: &Session) -> Fallible<Self::List>;
+    fn image_url(self, url: &str, session: &Session) -> Fallible<Self::List>;
+    fn link(self, url: &str, session: &Session) -> Fallible<Self::List>;
+    fn topic(self, id: &str, name: &str) -> Self::List;
+    fn mention(self, hash: &str, name: &str) -> Self::List;
 }
 
 impl ContentList for List {
@@ -134,19 +188,29 @@ impl ContentList for List {
         self
     }
 
-    fn image_path(mut self, path: &str, client: &Client) -> Fallible<List> {
-        self.push(Content::image_path(path, client)?);
+    fn image(mut self, bytes: Vec<u8>, session: &Session) -> Fallible<List> {
+        self.push(Content::image(bytes, session)?);
         Ok(self)
     }
 
-    fn image_url(mut self, path: &str, url: &str, client: &Client) -> Fallible<List> {
-        self.push(Content::image_url(path, url, client)?);
+    fn image_url(mut self, url: &str, session: &Session) -> Fallible<List> {
+        self.push(Content::image_url(url, session)?);
         Ok(self)
     }
 
-    fn link(mut self, url: &str, client: &Client) -> Fallible<List> {
-        self.push(Content::link(url, client)?);
+    fn link(mut self, url: &str, session: &Session) -> Fallible<List> {
+        self.push(Content::link(url, session)?);
         Ok(self)
     }
+
+    fn topic(mut self, id: &str, name: &str) -> List {
+        self.push(Content::topic(id, name));
+        self
+    }
+
+    fn mention(mut self, hash: &str, name: &str) -> List {
+        self.push(Content::mention(hash, name));
+        self
+    }
 }
 
@@ -9,7 +9,6 @@ pub trait ClientExt {
     type Client;
 
     fn build(cookie: &str) -> Fallible<Self::Client>;
-    fn is_valid(&self) -> Fallible<bool>; // cookie☆
 }
 
 impl ClientExt for Client {
@@ -17,7 +16,9 @@ impl ClientExt for Client {
 
     fn build(cookie: &str) -> Fallible<Self> {
         let mut headers = HeaderMap::new();
-        headers.insert(COOKIE, HeaderValue::from_str(cookie)?);
+        // The error of `from_str` may contain the cookie
+        headers.insert(COOKIE, HeaderValue::from_str(cookie)
+            .map_err(|_| err_msg("Cookie中含有无效字符"))?);
         headers.insert("x-requested-with", HeaderValue::from_static("sakjdbasd"));
 
         // WORKAROUND:
@@ -27,16 +28,105 @@ impl ClientExt for Client {
             .default_headers(headers)
             .build()?)
     }
+}
 
-    fn is_valid(&self) -> Fallible<bool> {
-        let mut resp = self.get("https://www.zhihu.com/inbox").send()?;
-
-        check_status_code(&mut resp)?;
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionState {
+    Valid,
+    // The cookie has expired or been revoked, a new one is needed
+    LoggedOut,
+    // Zhihu asks for a captcha or another verification in the browser
+    Captcha,
+    // The account is locked or banned
+    Banned,
+    // Landed somewhere else, with the url
+    Unknown(String),
+}
 
-        match resp.url().as_str() {
-            "https://www.zhihu.com/inbox" => Ok(true),
-            "https://www.zhihu.com/signup?next=%2Finbox" => Ok(false),
-            _ => unreachable!(),
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionState::Valid => write!(f, "已登录"),
+            SessionState::LoggedOut => write!(f, "Cookie已失效"),
+            SessionState::Captcha => write!(f, "需要在浏览器中完成验证"),
+            SessionState::Banned => write!(f, "账号已被限制"),
+            SessionState::Unknown(url) => write!(f, "未知状态（跳转至{}）", url),
         }
     }
 }
+
+pub(super) const INBOX: &str = "https://www.zhihu.com/inbox";
+
+// Zhihu serves the verification and ban pages with 4xx codes as well, these
+// markers tell them apart
+const CAPTCHA_MARKERS: &[&str] = &["unhuman", "captcha", "验证"];
+const BANNED_MARKERS: &[&str] = &["account/locked", "account/ban", "封禁", "冻结"];
+
+// Tell the state of the session from where `INBOX` ends up, and what it says
+// if that's an error page
+pub(super) fn classify(mut resp: Response) -> Fallible<SessionState> {
+    let status = resp.status().as_u16();
+    let url = resp.url().clone();
+    let body = if status >= 400 { resp.text()? } else { String::new() };
+    state_of(status, &url, &body)
+        .ok_or_else(|| err_msg(format!("检查登录状态失败，状态码 = {}，响应内容 = {}", status, body)))
+}
+
+// Only the path counts, so that the host, the locale and other query
+// parameters don't matter. None for a server error, which is transient.
+fn state_of(status: u16, url: &Url, body: &str) -> Option<SessionState> {
+    let zhihu = url.host_str().is_some_and(|h| h == "zhihu.com" || h.ends_with(".zhihu.com"));
+    let path = url.path().trim_end_matches('/');
+    let has = |markers: &[&str]| markers.iter().any(|m| body.contains(m));
+    Some(match path {
+        _ if !zhihu => SessionState::Unknown(url.to_string()),
+        "/signin" | "/signup" | "/login" => SessionState::LoggedOut,
+        _ if path.starts_with("/account/unhuman") || path.contains("captcha") || path.contains("verif")
+            => SessionState::Captcha,
+        _ if path.starts_with("/account/locked") || path.starts_with("/account/ban") => SessionState::Banned,
+        _ if status >= 500 => return None,
+        _ if status == 401 => SessionState::LoggedOut,
+        _ if status >= 400 && has(BANNED_MARKERS) => SessionState::Banned,
+        // Most 403s are the anti-bot check asking for a verification
+        _ if status >= 400 && (status == 403 || has(CAPTCHA_MARKERS)) => SessionState::Captcha,
+        _ if status >= 400 => SessionState::Unknown(format!("{}（HTTP {}）", url, status)),
+        "/inbox" => SessionState::Valid,
+        _ => SessionState::Unknown(url.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SessionState::*;
+
+    fn state(status: u16, url: &str, body: &str) -> Option<SessionState> {
+        state_of(status, &Url::parse(url).unwrap(), body)
+    }
+
+    #[test]
+    fn redirects() {
+        assert_eq!(state(200, "https://www.zhihu.com/inbox", ""), Some(Valid));
+        assert_eq!(state(200, "https://www.zhihu.com/inbox/", ""), Some(Valid));
+        assert_eq!(state(200, "https://www.zhihu.com/signin?next=%2Finbox", ""), Some(LoggedOut));
+        assert_eq!(state(200, "https://www.zhihu.com/account/unhuman?type=unhuman", ""), Some(Captcha));
+        assert_eq!(state(200, "https://zhihu.com/account/locked", ""), Some(Banned));
+        assert_eq!(state(200, "https://www.zhihu.com/explore", ""),
+                   Some(Unknown("https://www.zhihu.com/explore".to_owned())));
+        // Not the path of another host
+        assert_eq!(state(200, "https://example.com/inbox", ""),
+                   Some(Unknown("https://example.com/inbox".to_owned())));
+    }
+
+    #[test]
+    fn error_pages() {
+        assert_eq!(state(401, "https://www.zhihu.com/inbox", ""), Some(LoggedOut));
+        assert_eq!(state(403, "https://www.zhihu.com/inbox", ""), Some(Captcha));
+        assert_eq!(state(403, "https://www.zhihu.com/inbox", "账号已被封禁"), Some(Banned));
+        assert_eq!(state(429, "https://www.zhihu.com/inbox", "请完成验证"), Some(Captcha));
+        assert_eq!(state(404, "https://www.zhihu.com/signin", ""), Some(LoggedOut));
+        assert_eq!(state(404, "https://www.zhihu.com/inbox", ""),
+                   Some(Unknown("https://www.zhihu.com/inbox（HTTP 404）".to_owned())));
+        assert_eq!(state(502, "https://www.zhihu.com/inbox", ""), None);
+    }
+}
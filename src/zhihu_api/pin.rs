@@ -1,7 +1,7 @@
 use super::*;
-use reqwest::multipart::Form;
+use reqwest::{multipart::Form, StatusCode};
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug)]
 pub struct Pin {
     pub id: String,
 }
@@ -38,7 +38,7 @@ impl TryFrom<Url> for Pin {
 }
 
 impl Pin {
-    pub fn create(content: List, r#ref: Self, client: &Client) -> Fallible<Self> {
+    pub fn create(content: List, r#ref: Self, session: &Session) -> Fallible<Self> {
         // ensure the content list to be non-empty
         let content = if content.is_empty() {
             content.text("")
@@ -51,10 +51,10 @@ impl Pin {
             .text("version", "1")
             .text("source_pin_id", r#ref.id);
 
-        let mut resp = client
+        let mut resp = session.send(session
+            .client()
             .post("https://www.zhihu.com/api/v4/pins")
-            .multipart(form)
-            .send()?;
+            .multipart(form))?;
 
         check_status_code(&mut resp)?;
 
@@ -63,10 +63,125 @@ impl Pin {
         Ok(resp.json()?)
     }
 
-    pub fn delete(self, client: &Client) -> Fallible<()> {
-        client
-            .delete(&format!("https://www.zhihu.com/api/v4/pins/{}", self.id))
-            .send()?;
+    // A deleted pin is deleted already
+    pub fn delete(&self, session: &Session) -> Fallible<()> {
+        let mut resp = session.send(session
+            .client()
+            .delete(&self.url()))?;
+
+        if resp.status() != StatusCode::NOT_FOUND {
+            check_status_code(&mut resp)?;
+        }
         Ok(())
     }
+
+    // Replace the content, keeping the pin it reposts
+    pub fn edit(&self, content: List, session: &Session) -> Fallible<()> {
+        let form = Form::new()
+            .text("content", serde_json::to_string(&content)?)
+            .text("version", "1");
+
+        let mut resp = session.send(session
+            .client()
+            .put(&self.url())
+            .multipart(form))?;
+
+        check_status_code(&mut resp)
+    }
+
+    pub fn fetch(&self, session: &Session) -> Fallible<PinState> {
+        let mut resp = session.send(session
+            .client()
+            .get(&self.url()))?;
+
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(PinState { status: PinStatus::Deleted, ..PinState::default() });
+        }
+        check_status_code(&mut resp)?;
+
+        let raw: RawState = resp.json()?;
+        let status = if raw.is_deleted || raw.state == "deleted" {
+            PinStatus::Deleted
+        } else if raw.is_folded || raw.state == "folded" {
+            PinStatus::Folded
+        } else {
+            PinStatus::Visible
+        };
+        Ok(PinState {
+            status,
+            likes: raw.reaction_count.max(raw.like_count),
+            comments: raw.comment_count,
+            reposts: raw.repin_count,
+        })
+    }
+
+    fn url(&self) -> String {
+        format!("https://www.zhihu.com/api/v4/pins/{}", self.id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PinStatus {
+    #[default]
+    Visible,
+    // Hidden from the timeline by Zhihu, but still reachable
+    Folded,
+    Deleted,
+}
+
+impl PinStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinStatus::Visible => "visible",
+            PinStatus::Folded => "folded",
+            PinStatus::Deleted => "deleted",
+        }
+    }
+}
+
+impl FromStr for PinStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Fallible<Self> {
+        match s {
+            "visible" => Ok(PinStatus::Visible),
+            "folded" => Ok(PinStatus::Folded),
+            "deleted" => Ok(PinStatus::Deleted),
+            _ => bail!("无效的想法状态：{}", s),
+        }
+    }
+}
+
+impl fmt::Display for PinStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// A pin as currently seen on Zhihu
+#[derive(Clone, Debug, Default)]
+pub struct PinState {
+    pub status: PinStatus,
+    pub likes: i64,
+    pub comments: i64,
+    pub reposts: i64,
+}
+
+// Only the fields in use, the others are ignored
+#[derive(Debug, Deserialize)]
+struct RawState {
+    #[serde(default)]
+    is_deleted: bool,
+    #[serde(default)]
+    is_folded: bool,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    like_count: i64,
+    #[serde(default)]
+    reaction_count: i64,
+    #[serde(default)]
+    comment_count: i64,
+    #[serde(default)]
+    repin_count: i64,
 }
@@ -0,0 +1,196 @@
+use super::*;
+use crate::secret;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    os::unix::fs::OpenOptionsExt,
+};
+use serde::Serialize;
+use reqwest::{header::SET_COOKIE, RequestBuilder};
+
+// The login token of Zhihu, the session ends when it expires
+const TOKEN: &str = "z_c0";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Cookie {
+    value: String,
+    // RFC 3339, None if unknown or a session cookie
+    expires: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Jar {
+    // Hash of the configured cookie the jar started from, a different one
+    // means that the operator has supplied a new cookie, see `seed`
+    seed: u64,
+    cookies: BTreeMap<String, Cookie>,
+}
+
+// FNV-1a, which unlike the hasher of std stays the same across Rust releases
+fn seed(cookie: &str) -> u64 {
+    cookie.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+impl Jar {
+    fn from_header(header: &str, seed: u64) -> Self {
+        let cookies = header
+            .split(';')
+            .filter_map(|kv| {
+                let mut kv = kv.splitn(2, '=');
+                Some((kv.next()?.trim().to_owned(), Cookie {
+                    value: kv.next()?.trim().to_owned(),
+                    expires: None,
+                }))
+            })
+            .collect();
+        Jar { seed, cookies }
+    }
+
+    fn header(&self) -> String {
+        self.cookies
+            .iter()
+            .map(|(k, c)| format!("{}={}", k, c.value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    // Apply a `Set-Cookie` header, returns whether anything changed
+    fn set(&mut self, header: &str) -> bool {
+        let mut parts = header.split(';');
+        let (name, value) = match parts.next().map(|kv| kv.splitn(2, '=').collect::<Vec<_>>()) {
+            Some(ref kv) if kv.len() == 2 => (kv[0].trim().to_owned(), kv[1].trim().to_owned()),
+            _ => return false,
+        };
+
+        let mut expires = None;
+        for attr in parts {
+            let mut kv = attr.splitn(2, '=');
+            let key = kv.next().unwrap_or_default().trim().to_lowercase();
+            let val = kv.next().unwrap_or_default().trim();
+            match key.as_str() {
+                // Max-Age takes precedence over Expires
+                "max-age" => if let Ok(secs) = val.parse::<i64>() {
+                    expires = Some(Utc::now() + Duration::seconds(secs));
+                    break;
+                },
+                "expires" => {
+                    // Both `Wed, 21 Oct 2026 07:28:00 GMT` and `Wed, 21-Oct-2026 07:28:00 GMT` are in use
+                    expires = DateTime::parse_from_rfc2822(&val.replace('-', " "))
+                        .ok()
+                        .map(|tm| tm.with_timezone(&Utc));
+                },
+                _ => (),
+            }
+        }
+
+        if value.is_empty() || expires.is_some_and(|tm| tm <= Utc::now()) {
+            return self.cookies.remove(&name).is_some();
+        }
+        secret::register(&value);
+        let cookie = Cookie { value, expires: expires.map(|tm| tm.to_rfc3339()) };
+        match self.cookies.insert(name, cookie.clone()) {
+            Some(old) => old.value != cookie.value || old.expires != cookie.expires,
+            None => true,
+        }
+    }
+}
+
+// Cookies of the logged in account, kept up to date from the responses and
+// persisted in the jar file if any, so that a refreshed session survives restarts
+pub struct Session {
+    path: Option<String>,
+    jar: RefCell<Jar>,
+    client: RefCell<Client>,
+}
+
+// Never print the cookies
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Session {
+    pub fn new(cookie: &str, path: Option<&str>) -> Fallible<Self> {
+        let seed = seed(cookie);
+
+        let saved = match path {
+            Some(p) if std::path::Path::new(p).exists() => Some(serde_json::from_str::<Jar>(&secret::read_file(p)?)?),
+            _ => None,
+        };
+        // A jar of another cookie is replaced once the session refreshes,
+        // nothing is written before, since the config may still be rejected
+        let jar = match saved {
+            Some(jar) if jar.seed == seed => jar,
+            _ => Jar::from_header(cookie, seed),
+        };
+        for c in jar.cookies.values() {
+            secret::register(&c.value);
+        }
+
+        Ok(Session {
+            path: path.map(str::to_owned),
+            client: RefCell::new(Client::build(&jar.header())?),
+            jar: RefCell::new(jar),
+        })
+    }
+
+    // Cheap to clone, the connection pool is shared
+    pub fn client(&self) -> Client {
+        self.client.borrow().clone()
+    }
+
+    // Send a request built on `client`, taking the refreshed cookies. Every
+    // request to Zhihu goes through here.
+    pub fn send(&self, req: RequestBuilder) -> Fallible<Response> {
+        let resp = req.send()?;
+        self.update(&resp)?;
+        Ok(resp)
+    }
+
+    // Check the session against Zhihu
+    pub fn state(&self) -> Fallible<SessionState> {
+        super::http::classify(self.send(self.client().get(super::http::INBOX))?)
+    }
+
+    // When the login token expires, if known
+    pub fn expires(&self) -> Option<DateTime<Utc>> {
+        self.jar
+            .borrow()
+            .cookies
+            .get(TOKEN)?
+            .expires
+            .as_ref()
+            .and_then(|tm| DateTime::parse_from_rfc3339(tm).ok())
+            .map(|tm| tm.with_timezone(&Utc))
+    }
+
+    fn update(&self, resp: &Response) -> Fallible<()> {
+        // Every header is applied, so no short circuit
+        let mut changed = false;
+        for h in resp.headers().get_all(SET_COOKIE).iter().filter_map(|h| h.to_str().ok()) {
+            changed |= self.jar.borrow_mut().set(h);
+        }
+
+        if changed {
+            *self.client.borrow_mut() = Client::build(&self.jar.borrow().header())?;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Fallible<()> {
+        if let Some(ref path) = self.path {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(path)?;
+            serde_json::to_writer_pretty(&mut file, &*self.jar.borrow())?;
+        }
+        Ok(())
+    }
+}